@@ -0,0 +1,654 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    LedgerDirLock,
+    LedgerManifest,
+    LockError,
+    Network,
+    StorageMode,
+    aleo_network_ledger_dir,
+    hash::{HashingReader, HashingWriter},
+    snapshot::{RESTORE_STAGING_DIR, is_ledger_entry, is_ledger_path, remove_path, replace_ledger_contents},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// The name of the archive header, which is the first entry of an archive.
+pub const ARCHIVE_HEADER_FILE: &str = ".aleo-archive-header";
+
+/// The name of the archive manifest, which is the last entry of an archive.
+pub const ARCHIVE_MANIFEST_FILE: &str = ".aleo-archive-manifest";
+
+/// An error that occurs when exporting or importing a ledger archive.
+#[derive(Debug)]
+pub enum ArchiveError {
+    /// The ledger directory could not be locked.
+    Lock(LockError),
+    /// An I/O error occurred while reading or writing the ledger directory or the archive.
+    Io(io::Error),
+    /// The archive has no header or manifest, either of them is malformed, or they disagree.
+    MalformedManifest,
+    /// The archive belongs to another network.
+    NetworkMismatch {
+        /// The network the archive was imported for.
+        expected: Network,
+        /// The network recorded in the archive manifest.
+        found: Network,
+    },
+    /// The ledger directory to import into is not empty.
+    NotEmpty(PathBuf),
+    /// A file of the archive is not listed in its manifest, or is not a regular file within the ledger directory.
+    UnexpectedFile(String),
+    /// A file of the archive would replace the lock file, or a resource of the node other than the ledger.
+    ReservedFile(String),
+    /// A file listed in the archive manifest is missing from the archive.
+    MissingFile(String),
+    /// The contents of a file do not match the size or SHA-256 checksum listed in the archive manifest.
+    ChecksumMismatch(String),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lock(error) => write!(f, "{error}"),
+            Self::Io(error) => write!(f, "failed to access the ledger archive: {error}"),
+            Self::MalformedManifest => write!(f, "the ledger archive has no valid header and manifest"),
+            Self::NetworkMismatch { expected, found } => {
+                write!(f, "the ledger archive belongs to network '{found}', not '{expected}'")
+            }
+            Self::NotEmpty(path) => write!(f, "the ledger directory {} is not empty", path.display()),
+            Self::UnexpectedFile(path) => write!(f, "the ledger archive has an unexpected entry '{path}'"),
+            Self::ReservedFile(path) => {
+                write!(f, "the ledger archive has an entry '{path}' outside of the ledger data")
+            }
+            Self::MissingFile(path) => write!(f, "the ledger archive is missing the file '{path}'"),
+            Self::ChecksumMismatch(path) => write!(f, "the file '{path}' of the ledger archive is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Lock(error) => Some(error),
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<LockError> for ArchiveError {
+    fn from(error: LockError) -> Self {
+        Self::Lock(error)
+    }
+}
+
+/// A file of a ledger archive, as listed in its manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// The path of the file within the ledger directory, with `/` separators.
+    pub path: String,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// The SHA-256 checksum of the file, in lowercase hexadecimal.
+    pub sha256: String,
+}
+
+///
+/// The manifest of a ledger archive, which lists the files of the ledger along with their checksums.
+///
+/// The manifest is stored as `key = value` lines in the last entry of the archive, with one `file` line per file,
+/// i.e. `file = {sha256} {size} {path}`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveManifest {
+    /// The network of the ledger.
+    pub network: Network,
+    /// The on-disk format version of the ledger, if it has a version manifest (see `LedgerManifest`).
+    pub format_version: Option<u32>,
+    /// The files of the ledger, in the order they appear in the archive.
+    pub files: Vec<ArchiveEntry>,
+}
+
+impl ArchiveManifest {
+    /// Returns the total size of the files of the ledger, in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    ///
+    /// Parses the manifest from the contents of a manifest entry.
+    ///
+    /// The lines are split on `\n` alone, as a file path may end with `\r`.
+    ///
+    fn parse(contents: &str) -> Option<Self> {
+        let (mut network, mut format_version, mut files) = (None, None, Vec::new());
+        for line in contents.split('\n') {
+            match line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim_start()))
+            {
+                Some(("network", value)) => network = Some(Network::from_id(value.trim().parse().ok()?)),
+                Some(("format_version", value)) => format_version = Some(value.trim().parse().ok()?),
+                Some(("file", value)) => {
+                    let mut parts = value.splitn(3, ' ');
+                    let (sha256, size, path) = (parts.next()?, parts.next()?, parts.next()?);
+                    files.push(ArchiveEntry {
+                        path: path.to_string(),
+                        size: size.parse().ok()?,
+                        sha256: sha256.to_string(),
+                    });
+                }
+                _ => continue,
+            }
+        }
+        Some(Self {
+            network: network?,
+            format_version,
+            files,
+        })
+    }
+}
+
+impl fmt::Display for ArchiveManifest {
+    /// Formats the manifest as the contents of a manifest entry.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "network = {}", self.network.id())?;
+        if let Some(format_version) = self.format_version {
+            writeln!(f, "format_version = {format_version}")?;
+        }
+        for file in &self.files {
+            writeln!(f, "file = {} {} {}", file.sha256, file.size, file.path)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// The header of a ledger archive, which is its first entry, so that the archive is checked before anything is extracted.
+///
+/// The header is stored as `key = value` lines, with the network, the format version if any, and the number of files,
+/// i.e. `files = {count}`, which must all match the manifest.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ArchiveHeader {
+    /// The network of the ledger.
+    network: Network,
+    /// The on-disk format version of the ledger, if it has a version manifest.
+    format_version: Option<u32>,
+    /// The number of files of the ledger.
+    file_count: usize,
+}
+
+impl ArchiveHeader {
+    /// Parses the header from the contents of a header entry.
+    fn parse(contents: &str) -> Option<Self> {
+        let (mut network, mut format_version, mut file_count) = (None, None, None);
+        for line in contents.split('\n') {
+            match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("network", value)) => network = Some(Network::from_id(value.parse().ok()?)),
+                Some(("format_version", value)) => format_version = Some(value.parse().ok()?),
+                Some(("files", value)) => file_count = Some(value.parse().ok()?),
+                _ => continue,
+            }
+        }
+        Some(Self {
+            network: network?,
+            format_version,
+            file_count: file_count?,
+        })
+    }
+
+    /// Returns `true` if the given manifest has the network, format version and number of files of this header.
+    fn matches(&self, manifest: &ArchiveManifest) -> bool {
+        self.network == manifest.network
+            && self.format_version == manifest.format_version
+            && self.file_count == manifest.files.len()
+    }
+}
+
+impl fmt::Display for ArchiveHeader {
+    /// Formats the header as the contents of a header entry.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "network = {}", self.network.id())?;
+        if let Some(format_version) = self.format_version {
+            writeln!(f, "format_version = {format_version}")?;
+        }
+        writeln!(f, "files = {}", self.file_count)
+    }
+}
+
+///
+/// Exports the ledger directory of the given network and storage mode into a gzip-compressed tar archive,
+/// holding the lock of the ledger directory.
+///
+/// The files are streamed into the archive, preceded by a header naming the network and the number of files,
+/// and followed by a manifest listing their SHA-256 checksums.
+/// As with snapshots (see `SnapshotStore`), the lock file is not exported, and the other resources of the node,
+/// such as its private key, are kept next to the ledger directory, so they are not exported either.
+///
+pub fn export_ledger<W: Write>(
+    network: Network,
+    mode: &StorageMode,
+    writer: W,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let ledger_dir = aleo_network_ledger_dir(network, mode);
+    let _lock = LedgerDirLock::acquire_dir(&ledger_dir)?;
+    export_dir(&ledger_dir, network, writer)
+}
+
+///
+/// Imports a ledger archive written by `export_ledger` into the ledger directory of the given network and storage mode,
+/// holding the lock of the ledger directory, which must be empty.
+///
+/// An archive of another network is rejected by its header, before anything is extracted. Otherwise, the archive
+/// is extracted into a staging directory and verified against its manifest before it is moved into place,
+/// so a corrupted archive leaves the ledger directory empty.
+/// An archive with an entry that would replace the lock file or the staging directory is rejected.
+///
+pub fn import_ledger<R: Read>(
+    reader: R,
+    network: Network,
+    mode: &StorageMode,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let ledger_dir = aleo_network_ledger_dir(network, mode);
+    let _lock = LedgerDirLock::acquire_dir(&ledger_dir)?;
+
+    for entry in fs::read_dir(&ledger_dir)? {
+        let entry = entry?;
+        if is_ledger_entry(&entry, &[]) {
+            return Err(ArchiveError::NotEmpty(ledger_dir));
+        }
+    }
+
+    let staging = ledger_dir.join(RESTORE_STAGING_DIR);
+    fs::create_dir(&staging)?;
+    let is_reserved = |name: &str| !is_ledger_path(&ledger_dir.join(name), &[]);
+    let manifest = match extract(reader, &staging, network, &is_reserved) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            remove_path(&staging)?;
+            return Err(error);
+        }
+    };
+    replace_ledger_contents(&ledger_dir, &[])?;
+    Ok(manifest)
+}
+
+/// Writes the ledger contents of the given directory into an archive.
+fn export_dir<W: Write>(ledger_dir: &Path, network: Network, writer: W) -> Result<ArchiveManifest, ArchiveError> {
+    let mut manifest = ArchiveManifest {
+        network,
+        format_version: LedgerManifest::load(ledger_dir)?.map(|manifest| manifest.format_version),
+        files: Vec::new(),
+    };
+
+    // The files are listed up front, as the header counts them.
+    let mut files = Vec::new();
+    let mut pending = vec![(ledger_dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if prefix.is_empty() && !is_ledger_entry(&entry, &[]) {
+                continue;
+            }
+            let name = entry.file_name().into_string().map_err(|name| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is not a UTF-8 file name", name.to_string_lossy()),
+                )
+            })?;
+            let path = format!("{prefix}{name}");
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), format!("{path}/")));
+            } else if file_type.is_file() && !path.contains('\n') {
+                files.push((entry.path(), path));
+            } else {
+                return Err(ArchiveError::UnexpectedFile(path));
+            }
+        }
+    }
+
+    let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::fast()));
+    let header = ArchiveHeader {
+        network,
+        format_version: manifest.format_version,
+        file_count: files.len(),
+    };
+    append_text(&mut builder, ARCHIVE_HEADER_FILE, &header.to_string())?;
+    for (source, path) in files {
+        let file = File::open(source)?;
+        let size = file.metadata()?.len();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(size);
+        header.set_mode(0o644);
+        let mut reader = HashingReader::new(file.take(size));
+        builder.append_data(&mut header, &path, &mut reader)?;
+        manifest.files.push(ArchiveEntry {
+            path,
+            size,
+            sha256: reader.finish(),
+        });
+    }
+    append_text(&mut builder, ARCHIVE_MANIFEST_FILE, &manifest.to_string())?;
+    builder.into_inner()?.finish()?.flush()?;
+    Ok(manifest)
+}
+
+/// Appends a file with the given path and contents to the archive.
+fn append_text<W: Write>(builder: &mut tar::Builder<W>, path: &str, contents: &str) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, path, contents.as_bytes())
+}
+
+///
+/// Extracts the given archive into the given empty directory, and verifies it against its manifest.
+/// The header is checked before any file is extracted, and the entries whose top-level name is reserved are rejected.
+///
+fn extract<R: Read>(
+    reader: R,
+    dir: &Path,
+    network: Network,
+    is_reserved: &dyn Fn(&str) -> bool,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let mut entries = archive.entries()?;
+    let header = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry?;
+            if entry.path()?.as_os_str() != ARCHIVE_HEADER_FILE {
+                return Err(ArchiveError::MalformedManifest);
+            }
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            ArchiveHeader::parse(&contents).ok_or(ArchiveError::MalformedManifest)?
+        }
+        None => return Err(ArchiveError::MalformedManifest),
+    };
+    if header.network != network {
+        return Err(ArchiveError::NetworkMismatch {
+            expected: network,
+            found: header.network,
+        });
+    }
+
+    let mut extracted = BTreeMap::new();
+    let mut manifest = None;
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        if manifest.is_some() || path == ARCHIVE_HEADER_FILE {
+            return Err(ArchiveError::UnexpectedFile(path));
+        }
+        if path == ARCHIVE_MANIFEST_FILE {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            manifest = Some(ArchiveManifest::parse(&contents).ok_or(ArchiveError::MalformedManifest)?);
+            continue;
+        }
+
+        // Only regular files beneath the directory are extracted.
+        let relative = PathBuf::from(&path);
+        let is_nested = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        let is_counted = extracted.len() < header.file_count;
+        if !is_nested || !entry.header().entry_type().is_file() || extracted.contains_key(&path) || !is_counted {
+            return Err(ArchiveError::UnexpectedFile(path));
+        }
+        if relative
+            .components()
+            .next()
+            .is_some_and(|name| is_reserved(&name.as_os_str().to_string_lossy()))
+        {
+            return Err(ArchiveError::ReservedFile(path));
+        }
+        let target = dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = HashingWriter::new(File::create_new(&target)?);
+        let size = io::copy(&mut entry, &mut writer)?;
+        let (file, sha256) = writer.finish();
+        file.sync_all()?;
+        extracted.insert(path, (size, sha256));
+    }
+
+    let manifest = manifest
+        .filter(|manifest| header.matches(manifest))
+        .ok_or(ArchiveError::MalformedManifest)?;
+    for file in &manifest.files {
+        match extracted.remove(&file.path) {
+            Some((size, sha256)) if size == file.size && sha256 == file.sha256 => continue,
+            Some(_) => return Err(ArchiveError::ChecksumMismatch(file.path.clone())),
+            None => return Err(ArchiveError::MissingFile(file.path.clone())),
+        }
+    }
+    if let Some(path) = extracted.into_keys().next() {
+        return Err(ArchiveError::UnexpectedFile(path));
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StorageLayout;
+
+    /// Exports a test ledger with a few files, and returns the archive.
+    fn export_test_ledger(network: Network) -> Vec<u8> {
+        let mode = StorageMode::new_test(None);
+        let ledger_dir = crate::aleo_network_ledger_dir(network, &mode);
+        fs::create_dir_all(ledger_dir.join("db")).unwrap();
+        fs::write(ledger_dir.join("CURRENT"), "MANIFEST-000001\n").unwrap();
+        fs::write(ledger_dir.join("db/000001.sst"), vec![7u8; 100_000]).unwrap();
+        LedgerManifest::new(2, network, "test 1.0.0")
+            .store(&ledger_dir)
+            .unwrap();
+
+        let mut archive = Vec::new();
+        let manifest = export_ledger(network, &mode, &mut archive).unwrap();
+        assert_eq!(manifest.format_version, Some(2));
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(
+            manifest.total_size(),
+            100_000
+                + 16
+                + fs::metadata(ledger_dir.join(crate::LEDGER_MANIFEST_FILE))
+                    .unwrap()
+                    .len()
+        );
+        archive
+    }
+
+    #[test]
+    fn test_export_import() {
+        let archive = export_test_ledger(Network::CANARY);
+
+        let mode = StorageMode::new_test(None);
+        let manifest = import_ledger(archive.as_slice(), Network::CANARY, &mode).unwrap();
+        let ledger_dir = crate::aleo_network_ledger_dir(Network::CANARY, &mode);
+        assert_eq!(fs::read(ledger_dir.join("db/000001.sst")).unwrap(), vec![7u8; 100_000]);
+        assert_eq!(LedgerManifest::load(&ledger_dir).unwrap().unwrap().format_version, 2);
+        assert!(!ledger_dir.join(RESTORE_STAGING_DIR).exists());
+        assert!(!ledger_dir.join(ARCHIVE_MANIFEST_FILE).exists());
+        assert_eq!(manifest.network, Network::CANARY);
+
+        // A ledger is not imported over another one.
+        assert!(matches!(
+            import_ledger(archive.as_slice(), Network::CANARY, &mode),
+            Err(ArchiveError::NotEmpty(_))
+        ));
+        // Nor into another network.
+        assert!(matches!(
+            import_ledger(archive.as_slice(), Network::MAINNET, &StorageMode::new_test(None)),
+            Err(ArchiveError::NetworkMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_export_custom_mode() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let mode = StorageMode::Custom(tempdir.path().join("node"));
+        let layout = StorageLayout::new(Network::MAINNET, mode.clone());
+        let ledger_dir = layout.ledger_dir();
+        fs::create_dir_all(&ledger_dir).unwrap();
+        fs::create_dir_all(layout.logs_dir()).unwrap();
+        fs::create_dir_all(layout.snapshots_dir().join("before-v4")).unwrap();
+        fs::write(ledger_dir.join("CURRENT"), "MANIFEST-000001\n").unwrap();
+        fs::write(layout.node_key_path(), "APrivateKey1").unwrap();
+        fs::write(layout.peers_path(), "127.0.0.1:4130").unwrap();
+        fs::write(layout.config_path(), "[rest]").unwrap();
+        fs::write(layout.logs_dir().join("node.log"), "started").unwrap();
+
+        let mut archive = Vec::new();
+        let manifest = export_ledger(Network::MAINNET, &mode, &mut archive).unwrap();
+        let files = manifest.files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(files, ["CURRENT"]);
+
+        // The private key of the node is not in the archive.
+        let mut tar = tar::Archive::new(GzDecoder::new(archive.as_slice()));
+        let paths = tar
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [ARCHIVE_HEADER_FILE, "CURRENT", ARCHIVE_MANIFEST_FILE]);
+
+        // Nor does importing the archive require the other resources of the node to be removed.
+        let mode = StorageMode::Custom(tempdir.path().join("other"));
+        let layout = StorageLayout::new(Network::MAINNET, mode.clone());
+        fs::create_dir_all(layout.ledger_dir()).unwrap();
+        fs::write(layout.node_key_path(), "APrivateKey2").unwrap();
+        import_ledger(archive.as_slice(), Network::MAINNET, &mode).unwrap();
+        assert_eq!(fs::read_to_string(layout.node_key_path()).unwrap(), "APrivateKey2");
+        assert!(layout.ledger_dir().join("CURRENT").exists());
+    }
+
+    #[test]
+    fn test_import_reserved_entries() {
+        for reserved in [crate::LEDGER_LOCK_FILE, RESTORE_STAGING_DIR, ".aleo-restore/a"] {
+            let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+            append_text(&mut builder, ARCHIVE_HEADER_FILE, "network = 1\nfiles = 1\n").unwrap();
+            append_text(&mut builder, reserved, "1").unwrap();
+            let archive = builder.into_inner().unwrap().finish().unwrap();
+
+            let mode = StorageMode::new_test(None);
+            assert!(matches!(
+                import_ledger(archive.as_slice(), Network::TESTNET, &mode),
+                Err(ArchiveError::ReservedFile(path)) if path == reserved
+            ));
+        }
+    }
+
+    #[test]
+    fn test_import_header() {
+        let archive = export_test_ledger(Network::CANARY);
+        let mut tar = tar::Archive::new(GzDecoder::new(archive.as_slice()));
+        let mut entry = tar.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap().as_os_str(), ARCHIVE_HEADER_FILE);
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "network = 2\nformat_version = 2\nfiles = 3\n");
+
+        // An archive of another network is rejected before anything is extracted.
+        let tempdir = tempfile::TempDir::new().unwrap();
+        assert!(matches!(
+            extract(archive.as_slice(), tempdir.path(), Network::MAINNET, &|_| false),
+            Err(ArchiveError::NetworkMismatch {
+                found: Network::CANARY,
+                ..
+            })
+        ));
+        assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 0);
+
+        // So is an archive without a header, or with more files than its header counts.
+        for header in [None, Some("network = 1\nfiles = 0\n")] {
+            let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+            if let Some(header) = header {
+                append_text(&mut builder, ARCHIVE_HEADER_FILE, header).unwrap();
+            }
+            append_text(&mut builder, "CURRENT", "MANIFEST-000001\n").unwrap();
+            let archive = builder.into_inner().unwrap().finish().unwrap();
+            assert!(matches!(
+                extract(archive.as_slice(), tempdir.path(), Network::TESTNET, &|_| false),
+                Err(ArchiveError::MalformedManifest | ArchiveError::UnexpectedFile(_))
+            ));
+            assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 0);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_export_import_carriage_return() {
+        let mode = StorageMode::new_test(None);
+        let ledger_dir = crate::aleo_network_ledger_dir(Network::TESTNET, &mode);
+        fs::write(ledger_dir.join("CURRENT\r"), "MANIFEST-000001\n").unwrap();
+        let mut archive = Vec::new();
+        export_ledger(Network::TESTNET, &mode, &mut archive).unwrap();
+
+        // A file name ending with a carriage return keeps it in the manifest.
+        let mode = StorageMode::new_test(None);
+        let manifest = import_ledger(archive.as_slice(), Network::TESTNET, &mode).unwrap();
+        assert_eq!(manifest.files[0].path, "CURRENT\r");
+        let ledger_dir = crate::aleo_network_ledger_dir(Network::TESTNET, &mode);
+        assert!(ledger_dir.join("CURRENT\r").is_file());
+    }
+
+    #[test]
+    fn test_import_corrupted() {
+        let archive = export_test_ledger(Network::TESTNET);
+
+        // Flip a byte of the sst file within the (decompressed) tar stream.
+        let mut tar = Vec::new();
+        GzDecoder::new(archive.as_slice()).read_to_end(&mut tar).unwrap();
+        let offset = tar.windows(8).position(|window| window == [7u8; 8]).unwrap();
+        tar[offset] = 8;
+        let mut corrupted = GzEncoder::new(Vec::new(), Compression::fast());
+        corrupted.write_all(&tar).unwrap();
+        let corrupted = corrupted.finish().unwrap();
+
+        let mode = StorageMode::new_test(None);
+        assert!(matches!(
+            import_ledger(corrupted.as_slice(), Network::TESTNET, &mode),
+            Err(ArchiveError::ChecksumMismatch(path)) if path == "db/000001.sst"
+        ));
+        // The ledger directory is left empty.
+        let ledger_dir = crate::aleo_network_ledger_dir(Network::TESTNET, &mode);
+        let entries = fs::read_dir(&ledger_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [crate::LEDGER_LOCK_FILE]);
+    }
+}
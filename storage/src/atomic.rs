@@ -0,0 +1,237 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::lock::is_process_alive;
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The marker in the name of a temporary file, which is followed by the ID of the process that wrote it.
+pub(crate) const TEMP_FILE_MARKER: &str = ".aleo-tmp-";
+
+/// The extension appended to the name of a file's backup.
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// The counter distinguishing the temporary files written concurrently by this process.
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The options for writing a file atomically.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AtomicWriteOptions {
+    /// Whether to keep the previous version of the file, as `{file}.bak`.
+    pub backup: bool,
+    /// The Unix permissions of the file, e.g. `0o600`, which are otherwise given by the process umask.
+    /// This is ignored on other platforms.
+    pub mode: Option<u32>,
+}
+
+///
+/// Writes the given contents to the given file atomically, creating its parent directories if they do not exist.
+///
+/// See `write_atomic_with` for the guarantees.
+///
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    write_atomic_with(path, contents, &AtomicWriteOptions::default())
+}
+
+///
+/// Writes the given contents to the given file atomically, creating its parent directories if they do not exist.
+///
+/// The contents are written and synced to a temporary file in the same directory, which then replaces the file,
+/// and the directory is synced, so that after a crash the file holds either its previous or its new contents.
+/// Temporary files left behind by a crash are removed by `remove_stale_temp_files`.
+///
+pub fn write_atomic_with<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+    options: &AtomicWriteOptions,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let dir = parent_dir(path)?;
+    fs::create_dir_all(dir)?;
+
+    let temp_path = temp_path(path)?;
+    let result = (|| {
+        let mut open_options = OpenOptions::new();
+        open_options.write(true).create_new(true);
+        #[cfg(unix)]
+        if let Some(mode) = options.mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            open_options.mode(mode);
+        }
+        let mut file = open_options.open(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        drop(file);
+
+        if options.backup {
+            backup(path)?;
+        }
+        fs::rename(&temp_path, path)?;
+        sync_dir(dir)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+///
+/// Removes the temporary files left behind in the given directory by processes that are no longer running,
+/// and returns their paths. The directory is not searched recursively, and a missing directory is skipped.
+///
+pub fn remove_stale_temp_files<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut removed = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let Some(pid) = temp_file_pid(&entry.file_name()) else {
+            continue;
+        };
+        if pid != std::process::id() && !is_process_alive(pid) && entry.file_type()?.is_file() {
+            fs::remove_file(entry.path())?;
+            removed.push(entry.path());
+        }
+    }
+    Ok(removed)
+}
+
+/// Returns the ID of the process that wrote the temporary file with the given name, if it is a temporary file.
+pub(crate) fn temp_file_pid(file_name: &std::ffi::OsStr) -> Option<u32> {
+    let file_name = file_name.to_str()?;
+    let (_, suffix) = file_name.rsplit_once(TEMP_FILE_MARKER)?;
+    suffix.split('-').next()?.parse().ok()
+}
+
+/// Syncs the given directory, so that the creation, removal or renaming of its entries is durable.
+pub(crate) fn sync_dir(dir: &Path) -> io::Result<()> {
+    // Directories cannot be opened as files on Windows, where renames are durable once they return.
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Returns the directory of the given file, which is the current directory for a bare file name.
+fn parent_dir(path: &Path) -> io::Result<&Path> {
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Ok(Path::new(".")),
+        Some(parent) => Ok(parent),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        )),
+    }
+}
+
+/// Returns a unique temporary path next to the given file, i.e. `.{file}.aleo-tmp-{pid}-{counter}`.
+pub(crate) fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file", path.display())))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        "{TEMP_FILE_MARKER}{}-{}",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Replaces the backup of the given file with its current version, if the file exists.
+fn backup(path: &Path) -> io::Result<()> {
+    let mut backup_name = path.file_name().map(OsString::from).unwrap_or_default();
+    backup_name.push(format!(".{BACKUP_EXTENSION}"));
+    let backup_path = path.with_file_name(backup_name);
+    let temp_backup_path = temp_path(&backup_path)?;
+
+    // Link the current version to a temporary name, falling back to a copy, and then replace the backup.
+    match fs::hard_link(path, &temp_backup_path) {
+        Ok(()) => (),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(_) => {
+            fs::copy(path, &temp_backup_path)?;
+            File::open(&temp_backup_path)?.sync_all()?;
+        }
+    }
+    fs::rename(&temp_backup_path, &backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let path = tempdir.path().join("state").join("peers");
+
+        write_atomic(&path, b"first").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+
+        let options = AtomicWriteOptions {
+            backup: true,
+            ..Default::default()
+        };
+        write_atomic_with(&path, b"second", &options).unwrap();
+        write_atomic_with(&path, b"third", &options).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"third");
+        assert_eq!(fs::read(path.with_file_name("peers.bak")).unwrap(), b"second");
+
+        // No temporary files are left behind.
+        let mut entries = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, ["peers", "peers.bak"]);
+    }
+
+    #[test]
+    fn test_remove_stale_temp_files() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let stale = tempdir.path().join(format!(".peers{TEMP_FILE_MARKER}{}-0", u32::MAX));
+        let own = tempdir
+            .path()
+            .join(format!(".peers{TEMP_FILE_MARKER}{}-0", std::process::id()));
+        fs::write(&stale, b"").unwrap();
+        fs::write(&own, b"").unwrap();
+        fs::write(tempdir.path().join("peers"), b"").unwrap();
+
+        assert_eq!(
+            remove_stale_temp_files(tempdir.path()).unwrap(),
+            std::slice::from_ref(&stale)
+        );
+        assert!(!stale.exists());
+        assert!(own.exists());
+        assert!(
+            remove_stale_temp_files(tempdir.path().join("missing"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Lists and removes the production and development ledger directories.
+
+use aleo_std_storage::{LedgerInfo, Network, development_ledgers, development_root, production_ledgers};
+
+use std::{
+    path::PathBuf,
+    process::ExitCode,
+    time::{Duration, SystemTime},
+};
+
+const USAGE: &str = "\
+usage: aleo-ledgers <list|remove> [options]
+
+Lists or removes the production ledgers, and the development ledgers in the given root directories.
+
+options:
+    --root <dir>         search the given root directory for development ledgers (default: the development root)
+    --network <network>  only include the ledgers of the given network, e.g. `testnet`
+    --dev <id>           only include the development ledgers with the given ID
+    --older-than <days>  only include the ledgers not modified in the given number of days
+    --production         include the production ledgers when removing
+    --purge              also remove the snapshots, and the node keys and configuration files of development ledgers
+    --dry-run            list the ledgers that would be removed, without removing them";
+
+/// The command line options.
+#[derive(Default)]
+struct Options {
+    remove: bool,
+    roots: Vec<PathBuf>,
+    network: Option<Network>,
+    dev: Option<u16>,
+    older_than: Option<Duration>,
+    production: bool,
+    purge: bool,
+    dry_run: bool,
+}
+
+impl Options {
+    /// Parses the options from the command line arguments.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            remove: match args.next().as_deref() {
+                Some("list") => false,
+                Some("remove") => true,
+                _ => return Err("expected a `list` or `remove` command".to_string()),
+            },
+            ..Self::default()
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for `{arg}`"));
+            match arg.as_str() {
+                "--root" => options.roots.push(value()?.into()),
+                "--network" => options.network = Some(value()?.parse()?),
+                "--dev" => options.dev = Some(value()?.parse().map_err(|_| "invalid development ID")?),
+                "--older-than" => {
+                    let days: u64 = value()?.parse().map_err(|_| "invalid number of days")?;
+                    let secs = days.checked_mul(24 * 60 * 60).ok_or("too large a number of days")?;
+                    options.older_than = Some(Duration::from_secs(secs));
+                }
+                "--production" => options.production = true,
+                "--purge" => options.purge = true,
+                "--dry-run" => options.dry_run = true,
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            }
+        }
+        if options.purge && !options.remove {
+            return Err("`--purge` only applies to the `remove` command".to_string());
+        }
+        if options.roots.is_empty() {
+            options.roots.push(development_root().path);
+        }
+        Ok(options)
+    }
+
+    /// Returns `true` if the given ledger is selected by the options.
+    fn selects(&self, ledger: &LedgerInfo) -> bool {
+        let is_old = |age: &Duration| {
+            ledger
+                .modified
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|elapsed| elapsed >= *age)
+        };
+        self.network.is_none_or(|network| ledger.network == network)
+            && self.dev.is_none_or(|id| ledger.dev == Some(id))
+            && self.older_than.as_ref().is_none_or(is_old)
+            && (!self.remove || self.production || ledger.is_development())
+    }
+}
+
+/// Formats the given number of bytes, e.g. `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let (mut size, mut unit) = (bytes as f64, 0);
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", units[unit]),
+    }
+}
+
+/// Formats the time since the given ledger was last modified, e.g. `3d`.
+fn format_age(ledger: &LedgerInfo) -> String {
+    match ledger
+        .modified
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+    {
+        Some(elapsed) if elapsed.as_secs() >= 24 * 60 * 60 => format!("{}d", elapsed.as_secs() / (24 * 60 * 60)),
+        Some(elapsed) if elapsed.as_secs() >= 60 * 60 => format!("{}h", elapsed.as_secs() / (60 * 60)),
+        Some(elapsed) => format!("{}m", elapsed.as_secs() / 60),
+        None => "?".to_string(),
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut ledgers = production_ledgers().map_err(|error| error.to_string())?;
+    for root in &options.roots {
+        ledgers.extend(development_ledgers(root).map_err(|error| format!("{}: {error}", root.display()))?);
+    }
+
+    let mut failed = false;
+    for ledger in ledgers.iter().filter(|ledger| options.selects(ledger)) {
+        let mode = match ledger.dev {
+            Some(id) => format!("dev:{id}"),
+            None => "production".to_string(),
+        };
+        println!(
+            "{:<10} {:<12} {:>10} {:>5}  {}",
+            ledger.network,
+            mode,
+            format_size(ledger.size),
+            format_age(ledger),
+            ledger.path.display()
+        );
+        if options.remove && !options.dry_run {
+            let result = match options.purge {
+                true => ledger.purge(),
+                false => ledger.remove(),
+            };
+            if let Err(error) = result {
+                eprintln!("failed to remove {}: {error}", ledger.path.display());
+                failed = true;
+            }
+        }
+    }
+    match failed {
+        true => Err("some ledgers could not be removed".to_string()),
+        false => Ok(()),
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the given command line arguments.
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&[
+            "remove",
+            "--root",
+            "/src/snarkOS",
+            "--network",
+            "testnet",
+            "--dev",
+            "2",
+            "--older-than",
+            "7",
+            "--dry-run",
+        ])
+        .unwrap();
+        assert!(options.remove && options.dry_run && !options.production && !options.purge);
+        assert_eq!(options.roots, [PathBuf::from("/src/snarkOS")]);
+        assert_eq!(options.network, Some(Network::TESTNET));
+        assert_eq!(options.dev, Some(2));
+        assert_eq!(options.older_than, Some(Duration::from_secs(7 * 24 * 60 * 60)));
+
+        assert!(parse(&["remove", "--purge"]).unwrap().purge);
+
+        let options = parse(&["list", "--production"]).unwrap();
+        assert!(!options.remove && options.production);
+        assert_eq!(options.roots, [development_root().path]);
+    }
+
+    #[test]
+    fn test_parse_invalid_options() {
+        for args in [
+            &[][..],
+            &["prune"],
+            &["list", "--root"],
+            &["list", "--network", "devnet"],
+            &["list", "--dev", "70000"],
+            &["list", "--older-than", "-1"],
+            &["list", "--older-than", &u64::MAX.to_string()],
+            &["list", "--force"],
+            &["list", "--purge"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} should not parse");
+        }
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    StorageLayout,
+    atomic::{sync_dir, temp_path},
+    hash::{HashingReader, HashingWriter},
+    remove_stale_temp_files,
+    write_atomic,
+};
+
+use std::{
+    fmt,
+    fs::{self, File, FileTimes},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The extension of the marker file recording that an artifact was verified.
+const VERIFIED_EXTENSION: &str = "verified";
+
+/// An error that occurs when accessing the artifact cache.
+#[derive(Debug)]
+pub enum CacheError {
+    /// An I/O error occurred while accessing the cache.
+    Io(io::Error),
+    /// The artifact name is empty, starts with a `.`, or contains a character other than `A-Za-z0-9._-`.
+    InvalidName(String),
+    /// The checksum is not a lowercase hexadecimal SHA-256 checksum.
+    InvalidChecksum(String),
+    /// The contents of an inserted artifact do not match its checksum.
+    ChecksumMismatch {
+        /// The name of the artifact.
+        name: String,
+        /// The expected SHA-256 checksum.
+        expected: String,
+        /// The SHA-256 checksum of the contents.
+        found: String,
+    },
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to access the artifact cache: {error}"),
+            Self::InvalidName(name) => write!(f, "'{name}' is not a valid artifact name"),
+            Self::InvalidChecksum(checksum) => write!(f, "'{checksum}' is not a valid SHA-256 checksum"),
+            Self::ChecksumMismatch { name, expected, found } => {
+                write!(
+                    f,
+                    "the artifact '{name}' has checksum {found}, but {expected} was expected"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::InvalidName(_) | Self::InvalidChecksum(_) | Self::ChecksumMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// When the checksum of a cached artifact is verified.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Verification {
+    /// The checksum is verified every time the artifact is read.
+    #[default]
+    Always,
+    ///
+    /// The checksum is verified once, after which a marker file is trusted as long as the size of the artifact is unchanged.
+    ///
+    /// This avoids hashing large artifacts on every read, but an artifact corrupted without changing its size
+    /// is returned as intact.
+    ///
+    Lazy,
+}
+
+/// An artifact in the cache.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedArtifact {
+    /// The name of the artifact.
+    pub name: String,
+    /// The SHA-256 checksum of the artifact, in lowercase hexadecimal.
+    pub sha256: String,
+    /// The path of the artifact.
+    pub path: PathBuf,
+    /// The size of the artifact, in bytes.
+    pub size: u64,
+    /// The last time the artifact was inserted or read.
+    pub last_used: SystemTime,
+}
+
+///
+/// A content-addressed cache of large artifacts, such as proving parameters, which are stored by name and SHA-256 checksum.
+///
+/// An artifact is stored as `{name}-{sha256}` alongside a `.{name}-{sha256}.verified` marker, which records its size
+/// once its checksum was verified, and whose modification time records when it was last used. The cache does not
+/// fetch artifacts itself: a fetcher looks up an artifact with `get`, and on a miss, downloads and `insert`s it.
+///
+/// Inserted artifacts are verified before they are moved into place, so an interrupted or corrupted download
+/// never replaces a valid artifact, and `recover` removes whatever an interrupted insertion left behind.
+///
+#[derive(Clone, Debug)]
+pub struct ArtifactCache {
+    /// The directory of the cache.
+    dir: PathBuf,
+    /// The maximum total size of the artifacts, in bytes.
+    max_bytes: Option<u64>,
+    /// When the checksum of an artifact is verified.
+    verification: Verification,
+}
+
+impl ArtifactCache {
+    /// Initializes the artifact cache in the given directory.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: None,
+            verification: Verification::default(),
+        }
+    }
+
+    /// Initializes the artifact cache in the `artifacts` directory of the resources of the given layout.
+    pub fn for_layout(layout: &StorageLayout) -> Self {
+        Self::new(layout.resources_dir().join("artifacts"))
+    }
+
+    /// Sets the maximum total size of the artifacts, beyond which the least recently used artifacts are evicted.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Sets when the checksum of an artifact is verified.
+    pub fn verification(mut self, verification: Verification) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Returns the directory of the cache.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    ///
+    /// Returns the path of the given artifact if it is cached and intact, and marks it as used.
+    ///
+    /// An artifact that fails verification is removed, and reported as missing, so it is fetched again.
+    ///
+    pub fn get(&self, name: &str, sha256: &str) -> Result<Option<PathBuf>, CacheError> {
+        let path = self.artifact_path(name, sha256)?;
+        let size = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let marker = marker_path(&path);
+        let is_verified = self.verification == Verification::Lazy && read_marker(&marker)? == Some(size);
+        if !is_verified {
+            let mut reader = HashingReader::new(File::open(&path)?);
+            io::copy(&mut reader, &mut io::sink())?;
+            if reader.finish() != sha256 {
+                self.remove(name, sha256)?;
+                return Ok(None);
+            }
+            write_atomic(&marker, format!("size = {size}\n"))?;
+        }
+        touch(&marker)?;
+        Ok(Some(path))
+    }
+
+    ///
+    /// Inserts the artifact read from the given reader, and returns its path.
+    ///
+    /// The artifact is streamed into a temporary file, and only replaces a cached artifact once its checksum
+    /// is verified. The least recently used artifacts are then evicted if the cache exceeds its maximum size.
+    ///
+    pub fn insert<R: Read>(&self, name: &str, sha256: &str, reader: R) -> Result<PathBuf, CacheError> {
+        let path = self.artifact_path(name, sha256)?;
+        fs::create_dir_all(&self.dir)?;
+
+        let temp_path = temp_path(&path)?;
+        let result = (|| {
+            let mut writer = HashingWriter::new(File::create_new(&temp_path)?);
+            let size = io::copy(&mut { reader }, &mut writer)?;
+            let (file, found) = writer.finish();
+            if found != sha256 {
+                return Err(CacheError::ChecksumMismatch {
+                    name: name.to_string(),
+                    expected: sha256.to_string(),
+                    found,
+                });
+            }
+            file.sync_all()?;
+            fs::rename(&temp_path, &path)?;
+            sync_dir(&self.dir)?;
+            Ok(size)
+        })();
+        let size = match result {
+            Ok(size) => size,
+            Err(error) => {
+                let _ = fs::remove_file(&temp_path);
+                return Err(error);
+            }
+        };
+        write_atomic(marker_path(&path), format!("size = {size}\n"))?;
+
+        if let Some(max_bytes) = self.max_bytes {
+            self.evict_except(max_bytes, Some(&path))?;
+        }
+        Ok(path)
+    }
+
+    /// Removes the given artifact, if it is cached.
+    pub fn remove(&self, name: &str, sha256: &str) -> Result<(), CacheError> {
+        let path = self.artifact_path(name, sha256)?;
+        for path in [marker_path(&path), path] {
+            match fs::remove_file(&path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+                _ => continue,
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the cached artifacts, from the least to the most recently used.
+    pub fn artifacts(&self) -> Result<Vec<CachedArtifact>, CacheError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut artifacts = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some((name, sha256)) = file_name.to_str().and_then(parse_artifact_name) else {
+                continue;
+            };
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            // An artifact without a marker was never verified, and is the first to be evicted.
+            let last_used = fs::metadata(marker_path(&path))
+                .and_then(|marker| marker.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            artifacts.push(CachedArtifact {
+                name: name.to_string(),
+                sha256: sha256.to_string(),
+                path,
+                size: metadata.len(),
+                last_used,
+            });
+        }
+        artifacts.sort_by(|a, b| (a.last_used, &a.path).cmp(&(b.last_used, &b.path)));
+        Ok(artifacts)
+    }
+
+    /// Evicts the least recently used artifacts until their total size is within the given budget, and returns them.
+    pub fn evict(&self, max_bytes: u64) -> Result<Vec<CachedArtifact>, CacheError> {
+        self.evict_except(max_bytes, None)
+    }
+
+    ///
+    /// Removes whatever interrupted insertions left behind, i.e. temporary files and markers without an artifact,
+    /// and returns their paths.
+    ///
+    /// This is meant to be called on startup, while no other process uses the cache.
+    ///
+    pub fn recover(&self) -> Result<Vec<PathBuf>, CacheError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut removed = remove_stale_temp_files(&self.dir)?;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_marker = path
+                .extension()
+                .is_some_and(|extension| extension == VERIFIED_EXTENSION);
+            if is_marker && !artifact_of_marker(&path).exists() {
+                fs::remove_file(&path)?;
+                removed.push(path);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Evicts the least recently used artifacts, other than the given one, until their total size is within the given budget.
+    fn evict_except(&self, max_bytes: u64, keep: Option<&Path>) -> Result<Vec<CachedArtifact>, CacheError> {
+        let artifacts = self.artifacts()?;
+        let mut total: u64 = artifacts.iter().map(|artifact| artifact.size).sum();
+        let mut evicted = Vec::new();
+        for artifact in artifacts {
+            if total <= max_bytes {
+                break;
+            }
+            if Some(artifact.path.as_path()) == keep {
+                continue;
+            }
+            self.remove(&artifact.name, &artifact.sha256)?;
+            total -= artifact.size;
+            evicted.push(artifact);
+        }
+        Ok(evicted)
+    }
+
+    /// Returns the path of the given artifact, after validating its name and checksum.
+    fn artifact_path(&self, name: &str, sha256: &str) -> Result<PathBuf, CacheError> {
+        let is_valid_name = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if !is_valid_name {
+            return Err(CacheError::InvalidName(name.to_string()));
+        }
+        if !is_sha256(sha256) {
+            return Err(CacheError::InvalidChecksum(sha256.to_string()));
+        }
+        Ok(self.dir.join(format!("{name}-{sha256}")))
+    }
+}
+
+/// Returns `true` if the given string is a lowercase hexadecimal SHA-256 checksum.
+fn is_sha256(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Parses the name and checksum of an artifact from its file name, i.e. `{name}-{sha256}`.
+fn parse_artifact_name(file_name: &str) -> Option<(&str, &str)> {
+    let (name, sha256) = file_name.rsplit_once('-')?;
+    (!name.is_empty() && !name.starts_with('.') && is_sha256(sha256)).then_some((name, sha256))
+}
+
+/// Returns the path of the marker of the given artifact, i.e. `.{name}-{sha256}.verified`.
+fn marker_path(artifact: &Path) -> PathBuf {
+    let file_name = artifact.file_name().unwrap_or_default().to_string_lossy();
+    artifact.with_file_name(format!(".{file_name}.{VERIFIED_EXTENSION}"))
+}
+
+/// Returns the path of the artifact of the given marker.
+fn artifact_of_marker(marker: &Path) -> PathBuf {
+    let file_name = marker.file_stem().unwrap_or_default().to_string_lossy();
+    marker.with_file_name(file_name.trim_start_matches('.'))
+}
+
+/// Returns the size of the artifact recorded in the given marker, if the marker exists.
+fn read_marker(marker: &Path) -> io::Result<Option<u64>> {
+    match fs::read_to_string(marker) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .find_map(|line| line.strip_prefix("size = "))
+            .and_then(|size| size.trim().parse().ok())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Marks the given marker as used now.
+fn touch(marker: &Path) -> io::Result<()> {
+    File::options()
+        .write(true)
+        .open(marker)?
+        .set_times(FileTimes::new().set_modified(SystemTime::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::to_hex;
+    use sha2::{Digest, Sha256};
+    use std::time::Duration;
+
+    /// Returns the SHA-256 checksum of the given contents.
+    fn sha256(contents: &[u8]) -> String {
+        to_hex(&Sha256::digest(contents))
+    }
+
+    #[test]
+    fn test_artifact_cache() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let cache = ArtifactCache::new(tempdir.path());
+        let (contents, checksum) = (b"powers of tau".as_slice(), sha256(b"powers of tau"));
+
+        assert_eq!(cache.get("srs.usrs", &checksum).unwrap(), None);
+        assert!(matches!(
+            cache.insert("srs.usrs", &sha256(b"other"), contents),
+            Err(CacheError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            cache.get("../srs", &checksum),
+            Err(CacheError::InvalidName(_))
+        ));
+        assert!(matches!(cache.get("srs", "abc"), Err(CacheError::InvalidChecksum(_))));
+
+        let path = cache.insert("srs.usrs", &checksum, contents).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), contents);
+        assert_eq!(cache.get("srs.usrs", &checksum).unwrap(), Some(path.clone()));
+
+        // An artifact is verified on every read by default, and removed if it is corrupted.
+        fs::write(&path, b"powers of tax").unwrap();
+        assert_eq!(cache.get("srs.usrs", &checksum).unwrap(), None);
+        assert!(cache.artifacts().unwrap().is_empty());
+
+        // A lazily verified artifact is only re-verified once its size changes.
+        let cache = cache.verification(Verification::Lazy);
+        let path = cache.insert("srs.usrs", &checksum, contents).unwrap();
+        fs::write(&path, b"powers of tax").unwrap();
+        assert_eq!(cache.get("srs.usrs", &checksum).unwrap(), Some(path.clone()));
+        fs::write(&path, b"powers of tau!").unwrap();
+        assert_eq!(cache.get("srs.usrs", &checksum).unwrap(), None);
+    }
+
+    #[test]
+    fn test_artifact_cache_eviction() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let cache = ArtifactCache::new(tempdir.path()).max_bytes(25);
+
+        let checksums = ["a", "b", "c"].map(|name| {
+            let contents = name.repeat(10);
+            let checksum = sha256(contents.as_bytes());
+            cache.insert(name, &checksum, contents.as_bytes()).unwrap();
+            // Ensure distinct usage times.
+            let marker = marker_path(&cache.artifact_path(name, &checksum).unwrap());
+            let used = SystemTime::now() - Duration::from_secs(100);
+            File::options()
+                .write(true)
+                .open(marker)
+                .unwrap()
+                .set_times(FileTimes::new().set_modified(used))
+                .unwrap();
+            checksum
+        });
+        // Inserting `c` evicted `a`, the least recently used artifact.
+        let names = |cache: &ArtifactCache| {
+            cache
+                .artifacts()
+                .unwrap()
+                .into_iter()
+                .map(|a| a.name)
+                .collect::<Vec<_>>()
+        };
+        let mut cached = names(&cache);
+        cached.sort();
+        assert_eq!(cached, ["b", "c"]);
+
+        // Reading `b` makes `c` the least recently used artifact.
+        cache.get("b", &checksums[1]).unwrap().unwrap();
+        assert_eq!(cache.evict(10).unwrap()[0].name, "c");
+        assert_eq!(names(&cache), ["b"]);
+    }
+
+    #[test]
+    fn test_artifact_cache_recover() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let cache = ArtifactCache::new(tempdir.path());
+        let checksum = sha256(b"keys");
+        let path = cache.artifact_path("keys", &checksum).unwrap();
+
+        // An insertion interrupted while writing the artifact, or before its marker was written.
+        let temp = tempdir.path().join(format!(
+            ".keys-{checksum}{}{}-0",
+            crate::atomic::TEMP_FILE_MARKER,
+            u32::MAX
+        ));
+        fs::write(&temp, b"ke").unwrap();
+        fs::write(marker_path(&path), "size = 4\n").unwrap();
+        assert_eq!(cache.recover().unwrap().len(), 2);
+        assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 0);
+
+        // An artifact without a marker is verified when it is read.
+        fs::write(&path, b"keys").unwrap();
+        assert_eq!(cache.get("keys", &checksum).unwrap(), Some(path.clone()));
+        assert_eq!(read_marker(&marker_path(&path)).unwrap(), Some(4));
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::StorageMode;
+
+use clap::{
+    Arg,
+    Command,
+    Error,
+    builder::{TypedValueParser, ValueParserFactory},
+    error::ErrorKind,
+};
+use std::ffi::OsStr;
+
+///
+/// A `clap` value parser for `StorageMode`, accepting `production`, `dev:{id}`, `custom:{path}` or `test`.
+///
+/// It is used by default for `StorageMode` arguments, e.g. `#[arg(long)] storage: StorageMode`.
+///
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StorageModeParser;
+
+impl TypedValueParser for StorageModeParser {
+    type Value = StorageMode;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<Self::Value, Error> {
+        let arg = arg.map(|arg| arg.to_string()).unwrap_or_else(|| "...".to_string());
+        let invalid = |reason: &dyn std::fmt::Display| {
+            Error::raw(
+                ErrorKind::InvalidValue,
+                format!("invalid value for '{arg}': {reason}\n"),
+            )
+            .with_cmd(cmd)
+        };
+        let value = value.to_str().ok_or_else(|| invalid(&"the value is not valid UTF-8"))?;
+        value.parse().map_err(|error| invalid(&error))
+    }
+}
+
+impl ValueParserFactory for StorageMode {
+    type Parser = StorageModeParser;
+
+    fn value_parser() -> Self::Parser {
+        StorageModeParser
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("node").arg(
+            Arg::new("storage")
+                .long("storage")
+                .value_parser(clap::value_parser!(StorageMode)),
+        )
+    }
+
+    #[test]
+    fn test_storage_mode_parser() {
+        let matches = command().try_get_matches_from(["node", "--storage", "dev:2"]).unwrap();
+        assert_eq!(
+            matches.get_one::<StorageMode>("storage"),
+            Some(&StorageMode::Development(2))
+        );
+
+        let error = command()
+            .try_get_matches_from(["node", "--storage", "staging"])
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidValue);
+    }
+}
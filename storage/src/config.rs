@@ -0,0 +1,426 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Network, StorageLayout, StorageMode};
+
+use serde::de::DeserializeOwned;
+use std::{collections::BTreeMap, fmt, fs, io, path::PathBuf};
+use toml::{Table, Value};
+
+/// The name of the base configuration file, in the Aleo configuration directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// The default prefix of the environment variables overriding configuration values, e.g. `ALEO_CONFIG_REST__PORT`.
+pub const CONFIG_ENV_PREFIX: &str = "ALEO_CONFIG_";
+
+/// An error that occurs when loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A configuration file could not be read.
+    Io {
+        /// The path of the configuration file.
+        path: PathBuf,
+        /// The underlying error.
+        error: io::Error,
+    },
+    /// A configuration file is not valid TOML.
+    Parse {
+        /// The path of the configuration file.
+        path: PathBuf,
+        /// The parse error.
+        message: String,
+    },
+    /// A configuration value does not have the requested type.
+    InvalidValue {
+        /// The key of the value, e.g. `rest.port`.
+        key: String,
+        /// The layer that supplied the value.
+        source: ConfigSource,
+        /// The deserialization error.
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "failed to read the configuration file {}: {error}", path.display()),
+            Self::Parse { path, message } => {
+                write!(
+                    f,
+                    "failed to parse the configuration file {}: {message}",
+                    path.display()
+                )
+            }
+            Self::InvalidValue { key, source, message } => {
+                write!(f, "invalid configuration value '{key}' (from {source}): {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            Self::Parse { .. } | Self::InvalidValue { .. } => None,
+        }
+    }
+}
+
+/// The layer that supplied a configuration value, in increasing order of precedence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// The base configuration file, i.e. `config.toml` in the Aleo configuration directory.
+    Base(PathBuf),
+    /// The configuration file of the network, e.g. `config-mainnet.toml` in the Aleo configuration directory.
+    Network(PathBuf),
+    /// The configuration file of the storage mode (see `StorageLayout::config_path`).
+    Mode(PathBuf),
+    /// The given environment variable.
+    Environment(String),
+    /// An explicit override, e.g. from a command line argument.
+    Override,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base(path) | Self::Network(path) | Self::Mode(path) => write!(f, "{}", path.display()),
+            Self::Environment(name) => write!(f, "the environment variable {name}"),
+            Self::Override => write!(f, "an override"),
+        }
+    }
+}
+
+/// A configuration value, along with the layer that supplied it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigEntry {
+    /// The value.
+    pub value: Value,
+    /// The layer that supplied the value.
+    pub source: ConfigSource,
+}
+
+///
+/// A loader of the layered configuration of a node, for a given network and storage mode.
+///
+/// The configuration is a set of values keyed by their dotted path, e.g. `rest.port` for `port` in the `[rest]` table.
+/// Every value is taken from the layer of highest precedence that supplies it, in increasing order of precedence:
+/// 1. The base configuration file, `config.toml` in the Aleo configuration directory (see `aleo_config_dir`).
+/// 2. The configuration file of the network, e.g. `config-mainnet.toml` in the Aleo configuration directory.
+/// 3. The configuration file of the storage mode, e.g. `.config-mainnet-0.toml` in development mode
+///    (see `StorageLayout::config_path`), unless it is the configuration file of the network, as in production mode.
+/// 4. The environment variables, e.g. `ALEO_CONFIG_REST__PORT=3030` for `rest.port`, where `__` separates tables.
+///    Their values are parsed as TOML values, e.g. `true` or `[1, 2]`, or else taken as strings.
+/// 5. The explicit overrides given to `set`.
+///
+/// Missing configuration files are skipped.
+///
+#[derive(Clone, Debug)]
+pub struct ConfigLoader {
+    /// The storage layout of the node.
+    layout: StorageLayout,
+    /// The prefix of the environment variables, or `None` to ignore the environment.
+    env_prefix: Option<String>,
+    /// The explicit overrides.
+    overrides: Vec<(String, Value)>,
+}
+
+impl ConfigLoader {
+    /// Initializes a loader of the configuration of the given network and storage mode.
+    pub fn new(network: Network, mode: StorageMode) -> Self {
+        Self::for_layout(StorageLayout::new(network, mode))
+    }
+
+    /// Initializes a loader of the configuration of the given storage layout.
+    pub fn for_layout(layout: StorageLayout) -> Self {
+        Self {
+            layout,
+            env_prefix: Some(CONFIG_ENV_PREFIX.to_string()),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Sets the prefix of the environment variables overriding configuration values, or `None` to ignore them.
+    pub fn env_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.env_prefix = prefix.map(str::to_string);
+        self
+    }
+
+    /// Overrides the value of the given dotted key, e.g. `rest.port`, taking precedence over every other layer.
+    pub fn set<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.overrides.push((key.to_string(), value.into()));
+        self
+    }
+
+    /// Returns the configuration files of the layers, in increasing order of precedence, whether or not they exist.
+    pub fn files(&self) -> Vec<ConfigSource> {
+        let sources = [
+            ConfigSource::Base as fn(PathBuf) -> ConfigSource,
+            ConfigSource::Network,
+            ConfigSource::Mode,
+        ];
+        self.layout
+            .config_files()
+            .into_iter()
+            .zip(sources)
+            .map(|(path, source)| source(path))
+            .collect()
+    }
+
+    /// Loads the configuration from every layer.
+    pub fn load(&self) -> Result<NodeConfig, ConfigError> {
+        let mut config = NodeConfig::default();
+        for source in self.files() {
+            let (ConfigSource::Base(path) | ConfigSource::Network(path) | ConfigSource::Mode(path)) = &source else {
+                continue;
+            };
+            let contents = match fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => {
+                    return Err(ConfigError::Io {
+                        path: path.clone(),
+                        error,
+                    });
+                }
+            };
+            let table: Table = contents.parse().map_err(|error: toml::de::Error| ConfigError::Parse {
+                path: path.clone(),
+                message: error.message().to_string(),
+            })?;
+            config.files.push(path.clone());
+            config.merge_table("", table, &source);
+        }
+
+        if let Some(prefix) = &self.env_prefix {
+            // Variables whose name or value is not valid UTF-8 cannot be configuration values, and are skipped.
+            let mut vars: Vec<_> = std::env::vars_os()
+                .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+                .filter(|(name, _)| name.starts_with(prefix.as_str()))
+                .collect();
+            vars.sort();
+            for (name, value) in vars {
+                let key = name[prefix.len()..].to_lowercase().replace("__", ".");
+                config.insert(key, parse_env_value(&value), ConfigSource::Environment(name));
+            }
+        }
+
+        for (key, value) in &self.overrides {
+            config.insert(key.clone(), value.clone(), ConfigSource::Override);
+        }
+        Ok(config)
+    }
+}
+
+/// Parses the value of an environment variable as a TOML value, or else as a string.
+fn parse_env_value(value: &str) -> Value {
+    format!("value = {value}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// The configuration of a node, as loaded by a `ConfigLoader`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeConfig {
+    /// The values, keyed by their dotted path.
+    entries: BTreeMap<String, ConfigEntry>,
+    /// The configuration files that were loaded, in increasing order of precedence.
+    files: Vec<PathBuf>,
+}
+
+impl NodeConfig {
+    /// Returns the value of the given dotted key, e.g. `rest.port`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Returns the layer that supplied the value of the given dotted key.
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        self.entries.get(key).map(|entry| &entry.source)
+    }
+
+    /// Returns the value of the given dotted key, deserialized into the given type.
+    pub fn get_as<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        let Some(entry) = self.entries.get(key) else {
+            return Ok(None);
+        };
+        entry
+            .value
+            .clone()
+            .try_into()
+            .map(Some)
+            .map_err(|error: toml::de::Error| ConfigError::InvalidValue {
+                key: key.to_string(),
+                source: entry.source.clone(),
+                message: error.message().to_string(),
+            })
+    }
+
+    /// Returns every value and the layer that supplied it, sorted by their dotted key.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ConfigEntry)> {
+        self.entries.iter().map(|(key, entry)| (key.as_str(), entry))
+    }
+
+    /// Returns the configuration files that were loaded, in increasing order of precedence.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Inserts the given value, replacing the value of a layer of lower precedence.
+    fn insert(&mut self, key: String, value: Value, source: ConfigSource) {
+        // A table replaces the values nested beneath its key, and is flattened into them.
+        let prefix = format!("{key}.");
+        self.entries
+            .retain(|existing, _| *existing != key && !existing.starts_with(&prefix));
+        match value {
+            Value::Table(table) => self.merge_table(&key, table, &source),
+            value => {
+                self.entries.insert(key, ConfigEntry { value, source });
+            }
+        }
+    }
+
+    /// Inserts the values of the given table, nested beneath the given key, replacing those of a layer of lower precedence.
+    fn merge_table(&mut self, key: &str, table: Table, source: &ConfigSource) {
+        for (name, value) in table {
+            let key = match key {
+                "" => name,
+                key => format!("{key}.{name}"),
+            };
+            match value {
+                Value::Table(table) => self.merge_table(&key, table, source),
+                value => self.insert(key, value, source.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scoped_aleo_root, tests::env_lock};
+
+    #[test]
+    fn test_config_layers() {
+        let aleo = tempfile::TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(aleo.path());
+        let node = aleo.path().join("node");
+        // The configuration file of a custom ledger directory is kept next to it.
+        let node_file = aleo.path().join(".config-node.toml");
+
+        fs::write(
+            aleo.path().join(CONFIG_FILE),
+            "log_level = 1\n[rest]\nport = 3030\nip = \"0.0.0.0\"\n",
+        )
+        .unwrap();
+        fs::write(aleo.path().join("config-testnet.toml"), "[rest]\nport = 3031\n").unwrap();
+        fs::write(aleo.path().join("config-mainnet.toml"), "[rest]\nport = 3032\n").unwrap();
+        fs::write(&node_file, "peers = [\"127.0.0.1:4130\"]\n").unwrap();
+
+        let loader = ConfigLoader::new(Network::TESTNET, StorageMode::Custom(node.clone())).env_prefix(None);
+        let config = loader.clone().set("log_level", 4).load().unwrap();
+        assert_eq!(config.files(), [
+            aleo.path().join(CONFIG_FILE),
+            aleo.path().join("config-testnet.toml"),
+            node_file.clone()
+        ]);
+        assert_eq!(config.get_as::<u16>("rest.port").unwrap(), Some(3031));
+        assert_eq!(
+            config.source("rest.port"),
+            Some(&ConfigSource::Network(aleo.path().join("config-testnet.toml")))
+        );
+        assert_eq!(config.get_as::<String>("rest.ip").unwrap().as_deref(), Some("0.0.0.0"));
+        assert_eq!(
+            config.source("rest.ip"),
+            Some(&ConfigSource::Base(aleo.path().join(CONFIG_FILE)))
+        );
+        assert_eq!(config.get_as::<Vec<String>>("peers").unwrap().unwrap(), [
+            "127.0.0.1:4130"
+        ]);
+        assert_eq!(config.source("peers"), Some(&ConfigSource::Mode(node_file.clone())));
+        assert_eq!(config.get("log_level"), Some(&Value::Integer(4)));
+        assert_eq!(config.source("log_level"), Some(&ConfigSource::Override));
+        assert!(matches!(
+            config.get_as::<bool>("rest.port"),
+            Err(ConfigError::InvalidValue {
+                source: ConfigSource::Network(_),
+                ..
+            })
+        ));
+        assert_eq!(config.get("missing"), None);
+
+        // In production mode, the network file is the mode file.
+        let config = ConfigLoader::new(Network::MAINNET, StorageMode::Production)
+            .env_prefix(None)
+            .load()
+            .unwrap();
+        assert_eq!(config.files().len(), 2);
+        assert_eq!(config.get_as::<u16>("rest.port").unwrap(), Some(3032));
+
+        fs::write(aleo.path().join(CONFIG_FILE), "[rest\n").unwrap();
+        assert!(matches!(loader.load(), Err(ConfigError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_config_environment() {
+        let aleo = tempfile::TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(aleo.path());
+        fs::write(aleo.path().join(CONFIG_FILE), "[rest]\nport = 3030\n").unwrap();
+
+        let _lock = env_lock();
+        std::env::set_var("ALEO_CONFIG_REST__PORT", "4040");
+        std::env::set_var("ALEO_CONFIG_NODE__NAME", "validator-1");
+        let config = ConfigLoader::new(Network::TESTNET, StorageMode::Production)
+            .load()
+            .unwrap();
+        std::env::remove_var("ALEO_CONFIG_REST__PORT");
+        std::env::remove_var("ALEO_CONFIG_NODE__NAME");
+
+        assert_eq!(config.get("rest.port"), Some(&Value::Integer(4040)));
+        assert_eq!(
+            config.source("rest.port"),
+            Some(&ConfigSource::Environment("ALEO_CONFIG_REST__PORT".to_string()))
+        );
+        assert_eq!(config.get("node.name"), Some(&Value::String("validator-1".to_string())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_config_environment_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let aleo = tempfile::TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(aleo.path());
+
+        // Variables that are not valid UTF-8 are skipped, whether or not they match the prefix.
+        let _lock = env_lock();
+        std::env::set_var("ALEO_CONFIG_REST__PORT", "4040");
+        std::env::set_var("ALEO_CONFIG_NODE__NAME", OsStr::from_bytes(b"validator-\xff"));
+        std::env::set_var(OsStr::from_bytes(b"ALEO_CONFIG_\xff"), "1");
+        let config = ConfigLoader::new(Network::TESTNET, StorageMode::Production).load();
+        std::env::remove_var("ALEO_CONFIG_REST__PORT");
+        std::env::remove_var("ALEO_CONFIG_NODE__NAME");
+        std::env::remove_var(OsStr::from_bytes(b"ALEO_CONFIG_\xff"));
+
+        let config = config.unwrap();
+        assert_eq!(config.get("rest.port"), Some(&Value::Integer(4040)));
+        assert_eq!(config.get("node.name"), None);
+    }
+}
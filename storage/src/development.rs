@@ -0,0 +1,253 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::StorageError;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+/// The environment variable that overrides the development root.
+pub const ALEO_DEV_ROOT_ENV: &str = "ALEO_DEV_ROOT";
+
+/// The name of the marker file that designates its directory as the development root.
+pub const DEV_ROOT_MARKER: &str = ".aleo-dev-root";
+
+/// The process-wide development root, set via `set_development_root`.
+static DEVELOPMENT_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Whether the development root is discovered from the ancestors of the current directory,
+/// set via `set_development_root_discovery`.
+static DISCOVERY: AtomicBool = AtomicBool::new(false);
+
+/// The source a development root was resolved from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DevRootSource {
+    /// The root given to `set_development_root`.
+    Explicit,
+    /// The `ALEO_DEV_ROOT` environment variable.
+    Environment,
+    /// The nearest ancestor of the current directory holding a `.aleo-dev-root` marker file.
+    Marker,
+    /// The root of the Cargo workspace holding the current directory.
+    Workspace,
+    /// The current directory, or `.` if it is unavailable.
+    CurrentDir,
+}
+
+/// The root directory of the development ledgers, along with the source it was resolved from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DevelopmentRoot {
+    /// The development root.
+    pub path: PathBuf,
+    /// The source of the development root.
+    pub source: DevRootSource,
+}
+
+///
+/// Sets the process-wide development root, in which development ledgers are stored.
+///
+/// The root takes precedence over the `ALEO_DEV_ROOT` environment variable and the current directory.
+///
+pub fn set_development_root<P: Into<PathBuf>>(root: P) {
+    *DEVELOPMENT_ROOT.write().unwrap_or_else(|e| e.into_inner()) = Some(root.into());
+}
+
+///
+/// Clears the process-wide development root, restoring the default resolution.
+///
+pub fn clear_development_root() {
+    *DEVELOPMENT_ROOT.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+///
+/// Sets whether the development root is discovered from the ancestors of the current directory (see `development_root`),
+/// which is disabled by default, as it moves the development ledgers of a node launched from a subdirectory.
+///
+pub fn set_development_root_discovery(enabled: bool) {
+    DISCOVERY.store(enabled, Ordering::Relaxed);
+}
+
+///
+/// Returns the root directory of the development ledgers, e.g. `/path/to/repo`.
+///
+/// The root is resolved in the following order of precedence:
+/// 1. The process-wide root given to `set_development_root`.
+/// 2. The `ALEO_DEV_ROOT` environment variable, if it is set and non-empty.
+/// 3. If enabled by `set_development_root_discovery`, the nearest ancestor of the current directory holding
+///    a `.aleo-dev-root` marker file, or else the root of the Cargo workspace holding the current directory,
+///    i.e. the nearest ancestor with a `Cargo.toml` declaring a `[workspace]`.
+/// 4. The current directory.
+///
+/// The ancestors are searched on every call, so a marker file created meanwhile is found.
+/// If the current directory is unavailable, the relative path `.` is returned instead of it.
+///
+pub fn development_root() -> DevelopmentRoot {
+    try_development_root().unwrap_or_else(|_| DevelopmentRoot {
+        path: PathBuf::from("."),
+        source: DevRootSource::CurrentDir,
+    })
+}
+
+///
+/// Returns the root directory of the development ledgers, as given by `development_root`,
+/// or an error if it depends on the current directory, which is unavailable.
+///
+pub(crate) fn try_development_root() -> Result<DevelopmentRoot, StorageError> {
+    resolve_development_root(|| std::env::current_dir().map_err(StorageError::MissingCurrentDirectory))
+}
+
+/// Returns the root directory of the development ledgers, as given by `development_root`, from the given current directory.
+fn resolve_development_root(
+    current_dir: impl FnOnce() -> Result<PathBuf, StorageError>,
+) -> Result<DevelopmentRoot, StorageError> {
+    let root = |path, source| Ok(DevelopmentRoot { path, source });
+    if let Some(path) = DEVELOPMENT_ROOT.read().unwrap_or_else(|e| e.into_inner()).clone() {
+        return root(path, DevRootSource::Explicit);
+    }
+    match std::env::var_os(ALEO_DEV_ROOT_ENV) {
+        Some(path) if !path.is_empty() => return root(PathBuf::from(path), DevRootSource::Environment),
+        _ => (),
+    }
+
+    let current_dir = current_dir()?;
+    let discovered = match DISCOVERY.load(Ordering::Relaxed) {
+        true => find_project_root(&current_dir),
+        false => None,
+    };
+    Ok(discovered.unwrap_or(DevelopmentRoot {
+        path: current_dir,
+        source: DevRootSource::CurrentDir,
+    }))
+}
+
+/// Returns the nearest ancestor of the given directory holding a marker file, or else the root of its Cargo workspace.
+fn find_project_root(dir: &Path) -> Option<DevelopmentRoot> {
+    let root = |path: &Path, source| DevelopmentRoot {
+        path: path.to_path_buf(),
+        source,
+    };
+    let marker = dir.ancestors().find(|dir| dir.join(DEV_ROOT_MARKER).is_file());
+    match marker {
+        Some(path) => Some(root(path, DevRootSource::Marker)),
+        None => dir
+            .ancestors()
+            .find(|dir| is_workspace_root(dir))
+            .map(|path| root(path, DevRootSource::Workspace)),
+    }
+}
+
+/// Returns `true` if the given directory holds the `Cargo.toml` of a Cargo workspace.
+fn is_workspace_root(dir: &Path) -> bool {
+    fs::read_to_string(dir.join("Cargo.toml"))
+        .is_ok_and(|manifest| manifest.lines().any(|line| line.trim() == "[workspace]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::env_lock;
+
+    #[test]
+    fn test_development_root() {
+        let _lock = env_lock();
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let workspace = tempdir.path().to_path_buf();
+        let nested = workspace.join("crates").join("node");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(workspace.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        let resolve = || resolve_development_root(|| Ok(nested.clone())).unwrap();
+
+        // The current directory is the default, even within a workspace.
+        assert_eq!(resolve(), DevelopmentRoot {
+            path: nested.clone(),
+            source: DevRootSource::CurrentDir,
+        });
+
+        // Unless the root is discovered.
+        set_development_root_discovery(true);
+        assert_eq!(resolve(), DevelopmentRoot {
+            path: workspace.clone(),
+            source: DevRootSource::Workspace,
+        });
+        // A marker created afterwards is found.
+        fs::write(nested.join(DEV_ROOT_MARKER), b"").unwrap();
+        assert_eq!(resolve(), DevelopmentRoot {
+            path: nested.clone(),
+            source: DevRootSource::Marker,
+        });
+        set_development_root_discovery(false);
+
+        // The current directory is only required if neither the environment variable nor the root is set.
+        let missing = || {
+            Err(StorageError::MissingCurrentDirectory(
+                std::io::ErrorKind::NotFound.into(),
+            ))
+        };
+        assert!(matches!(
+            resolve_development_root(missing),
+            Err(StorageError::MissingCurrentDirectory(_))
+        ));
+
+        std::env::set_var(ALEO_DEV_ROOT_ENV, "/from/env");
+        assert_eq!(resolve_development_root(missing).unwrap(), DevelopmentRoot {
+            path: PathBuf::from("/from/env"),
+            source: DevRootSource::Environment,
+        });
+
+        set_development_root("/from/api");
+        assert_eq!(resolve_development_root(missing).unwrap(), DevelopmentRoot {
+            path: PathBuf::from("/from/api"),
+            source: DevRootSource::Explicit,
+        });
+
+        clear_development_root();
+        std::env::remove_var(ALEO_DEV_ROOT_ENV);
+        assert_eq!(development_root().source, DevRootSource::CurrentDir);
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let nested = tempdir.path().join("crates").join("node");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_project_root(&nested), None);
+
+        fs::write(tempdir.path().join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        assert_eq!(
+            find_project_root(&nested),
+            Some(DevelopmentRoot {
+                path: tempdir.path().to_path_buf(),
+                source: DevRootSource::Workspace,
+            })
+        );
+
+        // A marker takes precedence over the workspace.
+        fs::write(nested.parent().unwrap().join(DEV_ROOT_MARKER), b"").unwrap();
+        assert_eq!(
+            find_project_root(&nested),
+            Some(DevelopmentRoot {
+                path: nested.parent().unwrap().to_path_buf(),
+                source: DevRootSource::Marker,
+            })
+        );
+    }
+}
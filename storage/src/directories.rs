@@ -0,0 +1,382 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{ALEO_DIRECTORY, StorageError, root::aleo_root_override};
+
+use dirs::home_dir;
+use std::{
+    convert::Infallible,
+    path::PathBuf,
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+/// The environment variable that selects the layout policy (`legacy`, `xdg` or `detect`).
+pub const ALEO_DIR_LAYOUT_ENV: &str = "ALEO_DIR_LAYOUT";
+
+/// The directory name for Aleo-related resources within the XDG base directories.
+const XDG_ALEO_DIRECTORY: &str = "aleo";
+
+/// The process-wide layout policy, set via `set_layout_policy`.
+static LAYOUT_POLICY: RwLock<Option<LayoutPolicy>> = RwLock::new(None);
+
+/// Whether an unrecognized `ALEO_DIR_LAYOUT` value was reported, which is only done once.
+static INVALID_POLICY_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// The on-disk layout of the Aleo directories.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DirectoryLayout {
+    /// Every resource is stored under a single `~/.aleo` directory.
+    Legacy,
+    /// Resources are split across the XDG base directories, e.g. `$XDG_DATA_HOME/aleo`.
+    Xdg,
+}
+
+/// The policy used to select the `DirectoryLayout`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LayoutPolicy {
+    /// Always use the legacy `~/.aleo` layout.
+    #[default]
+    Legacy,
+    /// Always use the XDG base directory layout.
+    Xdg,
+    /// Use the layout of an existing installation, or the legacy layout if there is none.
+    Detect,
+}
+
+impl std::str::FromStr for LayoutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "legacy" => Ok(Self::Legacy),
+            "xdg" => Ok(Self::Xdg),
+            "detect" | "auto" => Ok(Self::Detect),
+            other => Err(format!("unknown directory layout policy '{other}'")),
+        }
+    }
+}
+
+///
+/// Sets the process-wide layout policy, which takes precedence over the `ALEO_DIR_LAYOUT` environment variable.
+///
+pub fn set_layout_policy(policy: LayoutPolicy) {
+    *LAYOUT_POLICY.write().unwrap_or_else(|e| e.into_inner()) = Some(policy);
+}
+
+///
+/// Clears the process-wide layout policy, restoring the default resolution.
+///
+pub fn clear_layout_policy() {
+    *LAYOUT_POLICY.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+///
+/// Returns the layout policy in effect, which is the one given to `set_layout_policy`,
+/// otherwise the one named by the `ALEO_DIR_LAYOUT` environment variable, otherwise `LayoutPolicy::Legacy`.
+///
+/// An unrecognized `ALEO_DIR_LAYOUT` value is reported on stderr the first time it is seen, and ignored
+/// in favor of the legacy layout. `try_aleo_dir` returns `StorageError::InvalidLayoutPolicy` for it instead.
+///
+pub fn layout_policy() -> LayoutPolicy {
+    try_layout_policy().unwrap_or_else(|error| {
+        if !INVALID_POLICY_REPORTED.swap(true, Ordering::Relaxed) {
+            eprintln!("warning: {error}, using the legacy layout");
+        }
+        LayoutPolicy::Legacy
+    })
+}
+
+/// Returns the layout policy in effect, as given by `layout_policy`, or an error if `ALEO_DIR_LAYOUT` is unrecognized.
+fn try_layout_policy() -> Result<LayoutPolicy, StorageError> {
+    if let Some(policy) = *LAYOUT_POLICY.read().unwrap_or_else(|e| e.into_inner()) {
+        return Ok(policy);
+    }
+    match std::env::var_os(ALEO_DIR_LAYOUT_ENV) {
+        Some(policy) if !policy.is_empty() => policy
+            .to_str()
+            .and_then(|policy| policy.parse().ok())
+            .ok_or_else(|| StorageError::InvalidLayoutPolicy(policy.to_string_lossy().into_owned())),
+        _ => Ok(LayoutPolicy::default()),
+    }
+}
+
+///
+/// Returns the directory layout in effect.
+///
+/// An overridden Aleo root (see `set_aleo_root`) always uses the legacy layout.
+///
+pub fn directory_layout() -> DirectoryLayout {
+    if aleo_root_override().is_some() {
+        return DirectoryLayout::Legacy;
+    }
+    match layout_policy() {
+        LayoutPolicy::Legacy => DirectoryLayout::Legacy,
+        LayoutPolicy::Xdg => DirectoryLayout::Xdg,
+        LayoutPolicy::Detect => detect_directory_layout().unwrap_or(DirectoryLayout::Legacy),
+    }
+}
+
+///
+/// Returns the layout of an existing installation, if any.
+///
+/// If both layouts are present, the legacy layout wins, as it is the one older software writes to.
+///
+pub fn detect_directory_layout() -> Option<DirectoryLayout> {
+    if legacy_aleo_dir().is_dir() {
+        Some(DirectoryLayout::Legacy)
+    } else if matches!(xdg_dir("XDG_DATA_HOME", &[".local", "share"], home_or_fallback), Ok(path) if path.is_dir()) {
+        Some(DirectoryLayout::Xdg)
+    } else {
+        None
+    }
+}
+
+///
+/// Returns the directory for persistent data, such as the ledger.
+///
+/// In the legacy layout, the expected directory path is `~/.aleo/`.
+/// In the XDG layout, the expected directory path is `$XDG_DATA_HOME/aleo/`.
+///
+pub fn aleo_data_dir() -> PathBuf {
+    layout_dir("XDG_DATA_HOME", &[".local", "share"])
+}
+
+///
+/// Returns the directory for configuration files.
+///
+/// In the legacy layout, the expected directory path is `~/.aleo/`.
+/// In the XDG layout, the expected directory path is `$XDG_CONFIG_HOME/aleo/`.
+///
+pub fn aleo_config_dir() -> PathBuf {
+    layout_dir("XDG_CONFIG_HOME", &[".config"])
+}
+
+///
+/// Returns the directory for caches, such as downloaded parameters.
+///
+/// In the legacy layout, the expected directory path is `~/.aleo/`.
+/// In the XDG layout, the expected directory path is `$XDG_CACHE_HOME/aleo/`.
+///
+pub fn aleo_cache_dir() -> PathBuf {
+    layout_dir("XDG_CACHE_HOME", &[".cache"])
+}
+
+///
+/// Returns the directory for state that should persist across restarts, such as logs and peer lists.
+///
+/// In the legacy layout, the expected directory path is `~/.aleo/`.
+/// In the XDG layout, the expected directory path is `$XDG_STATE_HOME/aleo/`.
+///
+pub fn aleo_state_dir() -> PathBuf {
+    layout_dir("XDG_STATE_HOME", &[".local", "state"])
+}
+
+/// Returns the Aleo directory of the given XDG category under the layout in effect.
+fn layout_dir(xdg_var: &str, xdg_default: &[&str]) -> PathBuf {
+    match resolve_layout_dir(xdg_var, xdg_default, home_or_fallback) {
+        Ok(path) => path,
+        Err(never) => match never {},
+    }
+}
+
+///
+/// Returns the directory for persistent data, as given by `aleo_data_dir`, or an error if the home directory
+/// is required but cannot be determined, or if the layout policy is required but `ALEO_DIR_LAYOUT` is unrecognized.
+///
+pub(crate) fn try_aleo_data_dir() -> Result<PathBuf, StorageError> {
+    if aleo_root_override().is_none() {
+        try_layout_policy()?;
+    }
+    resolve_layout_dir("XDG_DATA_HOME", &[".local", "share"], || {
+        home_dir().ok_or(StorageError::MissingHomeDirectory)
+    })
+}
+
+/// Returns the Aleo directory of the given XDG category under the layout in effect, using the given home directory.
+fn resolve_layout_dir<E>(
+    xdg_var: &str,
+    xdg_default: &[&str],
+    home: impl Fn() -> Result<PathBuf, E>,
+) -> Result<PathBuf, E> {
+    if let Some(root) = aleo_root_override() {
+        return Ok(root);
+    }
+    match directory_layout() {
+        DirectoryLayout::Legacy => Ok(home()?.join(ALEO_DIRECTORY)),
+        DirectoryLayout::Xdg => xdg_dir(xdg_var, xdg_default, home),
+    }
+}
+
+///
+/// Returns the Aleo directory within the given XDG base directory, using the given home directory.
+/// Per the specification, the variable is ignored unless it holds an absolute path.
+///
+fn xdg_dir<E>(xdg_var: &str, xdg_default: &[&str], home: impl Fn() -> Result<PathBuf, E>) -> Result<PathBuf, E> {
+    let mut path = match std::env::var_os(xdg_var).map(PathBuf::from) {
+        Some(base) if base.is_absolute() => base,
+        _ => {
+            let mut base = home()?;
+            base.extend(xdg_default);
+            base
+        }
+    };
+    path.push(XDG_ALEO_DIRECTORY);
+    Ok(path)
+}
+
+///
+/// Returns the legacy Aleo directory, `~/.aleo/`.
+/// If the home directory cannot be determined, the current directory is used instead of it.
+///
+pub(crate) fn legacy_aleo_dir() -> PathBuf {
+    match home_or_fallback() {
+        Ok(home) => home.join(ALEO_DIRECTORY),
+        Err(never) => match never {},
+    }
+}
+
+///
+/// Returns the home directory, or else the current directory, or else the relative path `.`.
+///
+/// The infallible path helpers, such as `aleo_dir`, resolve a directory at runtime in every case;
+/// `try_aleo_dir` reports a missing home directory as an error instead.
+///
+fn home_or_fallback() -> Result<PathBuf, Infallible> {
+    Ok(home_dir()
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_else(|| PathBuf::from(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StorageMode, aleo_dir, aleo_ledger_dir, scoped_aleo_root, tests::env_lock};
+
+    #[test]
+    fn test_xdg_layout() {
+        let _lock = env_lock();
+
+        std::env::set_var("XDG_DATA_HOME", "/xdg/data");
+        std::env::set_var("XDG_CONFIG_HOME", "/xdg/config");
+        std::env::set_var("XDG_CACHE_HOME", "/xdg/cache");
+        std::env::set_var("XDG_STATE_HOME", "relative/state");
+        set_layout_policy(LayoutPolicy::Xdg);
+
+        assert_eq!(directory_layout(), DirectoryLayout::Xdg);
+        assert_eq!(aleo_dir(), PathBuf::from("/xdg/data/aleo"));
+        assert_eq!(aleo_data_dir(), PathBuf::from("/xdg/data/aleo"));
+        assert_eq!(aleo_config_dir(), PathBuf::from("/xdg/config/aleo"));
+        assert_eq!(aleo_cache_dir(), PathBuf::from("/xdg/cache/aleo"));
+        // Relative paths are ignored in favor of the default.
+        assert!(aleo_state_dir().ends_with(".local/state/aleo"));
+        assert_eq!(
+            aleo_ledger_dir(1, &StorageMode::Production),
+            PathBuf::from("/xdg/data/aleo/storage/ledger-1")
+        );
+
+        // An overridden root takes precedence over the layout.
+        {
+            let _guard = scoped_aleo_root("/root/override");
+            assert_eq!(directory_layout(), DirectoryLayout::Legacy);
+            assert_eq!(aleo_cache_dir(), PathBuf::from("/root/override"));
+        }
+
+        set_layout_policy(LayoutPolicy::Legacy);
+        assert_eq!(aleo_config_dir(), legacy_aleo_dir());
+        assert_eq!(aleo_dir(), legacy_aleo_dir());
+
+        // The environment variable applies once the process-wide policy is cleared.
+        clear_layout_policy();
+        std::env::set_var(ALEO_DIR_LAYOUT_ENV, "xdg");
+        assert_eq!(aleo_config_dir(), PathBuf::from("/xdg/config/aleo"));
+        std::env::remove_var(ALEO_DIR_LAYOUT_ENV);
+
+        for var in ["XDG_DATA_HOME", "XDG_CONFIG_HOME", "XDG_CACHE_HOME", "XDG_STATE_HOME"] {
+            std::env::remove_var(var);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_directory_layout() {
+        let _lock = env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let original_home = std::env::var_os("HOME");
+        std::env::set_var("HOME", home.path());
+        std::env::remove_var("XDG_DATA_HOME");
+        set_layout_policy(LayoutPolicy::Detect);
+
+        // Without an existing installation, the legacy layout is used.
+        assert_eq!(detect_directory_layout(), None);
+        assert_eq!(directory_layout(), DirectoryLayout::Legacy);
+        assert_eq!(aleo_dir(), home.path().join(ALEO_DIRECTORY));
+
+        // An existing XDG installation is followed.
+        let xdg_dir = home.path().join(".local").join("share").join(XDG_ALEO_DIRECTORY);
+        std::fs::create_dir_all(&xdg_dir).unwrap();
+        assert_eq!(detect_directory_layout(), Some(DirectoryLayout::Xdg));
+        assert_eq!(aleo_dir(), xdg_dir);
+        assert_eq!(aleo_config_dir(), home.path().join(".config").join(XDG_ALEO_DIRECTORY));
+
+        // Unless a legacy installation exists too.
+        std::fs::create_dir(home.path().join(ALEO_DIRECTORY)).unwrap();
+        assert_eq!(detect_directory_layout(), Some(DirectoryLayout::Legacy));
+        assert_eq!(aleo_dir(), home.path().join(ALEO_DIRECTORY));
+
+        clear_layout_policy();
+        match original_home {
+            Some(original_home) => std::env::set_var("HOME", original_home),
+            None => std::env::remove_var("HOME"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_layout_policy() {
+        let _lock = env_lock();
+        clear_layout_policy();
+        std::env::set_var(ALEO_DIR_LAYOUT_ENV, "flat");
+
+        // The unrecognized policy is ignored by the infallible helpers, but rejected by the fallible ones.
+        assert_eq!(layout_policy(), LayoutPolicy::Legacy);
+        assert!(matches!(
+            crate::try_aleo_dir(),
+            Err(StorageError::InvalidLayoutPolicy(policy)) if policy == "flat"
+        ));
+        // An overridden root does not depend on the layout.
+        {
+            let tempdir = tempfile::TempDir::new().unwrap();
+            let _guard = scoped_aleo_root(tempdir.path());
+            assert_eq!(crate::try_aleo_dir().unwrap(), tempdir.path());
+        }
+
+        std::env::remove_var(ALEO_DIR_LAYOUT_ENV);
+        assert!(!matches!(
+            crate::try_aleo_dir(),
+            Err(StorageError::InvalidLayoutPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn test_layout_policy_from_str() {
+        assert_eq!("legacy".parse::<LayoutPolicy>().unwrap(), LayoutPolicy::Legacy);
+        assert_eq!("XDG".parse::<LayoutPolicy>().unwrap(), LayoutPolicy::Xdg);
+        assert_eq!("auto".parse::<LayoutPolicy>().unwrap(), LayoutPolicy::Detect);
+        assert!("flat".parse::<LayoutPolicy>().is_err());
+    }
+}
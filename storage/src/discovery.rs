@@ -0,0 +1,290 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    LedgerDirLock,
+    LockError,
+    Network,
+    aleo_dir,
+    layout::{development_resources, kept_resources, production_resources},
+    snapshot::remove_path,
+};
+
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// A ledger directory found on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerInfo {
+    /// The ledger directory.
+    pub path: PathBuf,
+    /// The network of the ledger.
+    pub network: Network,
+    /// The development ID of the ledger, or `None` for a production ledger.
+    pub dev: Option<u16>,
+    /// The total size of the files in the ledger directory, in bytes.
+    pub size: u64,
+    /// The last time a file in the ledger directory was modified, if known.
+    pub modified: Option<SystemTime>,
+}
+
+impl LedgerInfo {
+    /// Returns `true` if the ledger is a development ledger.
+    pub const fn is_development(&self) -> bool {
+        self.dev.is_some()
+    }
+
+    ///
+    /// Removes the ledger directory, holding its lock, along with the resources stored next to it (see `StorageLayout`):
+    /// the BFT state of a production ledger, and every resource of a development ledger, such as its logs.
+    ///
+    /// The snapshots of the ledger, and the node key and configuration file of a development ledger, are kept,
+    /// so that removing a ledger never loses key material; see `LedgerInfo::purge` to remove them as well.
+    ///
+    /// Returns `LockError::AlreadyLocked` if the ledger directory is in use.
+    ///
+    pub fn remove(&self) -> Result<(), LockError> {
+        self.remove_with(false)
+    }
+
+    ///
+    /// Removes the ledger directory, as `LedgerInfo::remove` does, along with its snapshots,
+    /// and the node key and configuration file of a development ledger.
+    ///
+    pub fn purge(&self) -> Result<(), LockError> {
+        self.remove_with(true)
+    }
+
+    /// Removes the ledger directory, along with the resources stored next to it, including the kept ones if requested.
+    fn remove_with(&self, purge: bool) -> Result<(), LockError> {
+        let lock = LedgerDirLock::acquire_dir(&self.path)?;
+        let mut resources = match self.dev {
+            Some(id) => development_resources(&self.path, &self.network, id),
+            None => production_resources(&self.path),
+        };
+        if !purge {
+            let kept = kept_resources(&self.path, self.network, self.dev);
+            resources.retain(|resource| !kept.contains(resource));
+        }
+        for resource in resources {
+            remove_path(&resource)?;
+        }
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if entry.path() != lock.path() {
+                remove_path(&entry.path())?;
+            }
+        }
+        // The lock file is unlinked while it is still held, so a node starting meanwhile creates a new one,
+        // and the directory is then no longer empty, nor removed.
+        #[cfg(unix)]
+        fs::remove_file(lock.path())?;
+        // Elsewhere, a file cannot be removed while it is open, so the lock is released first.
+        #[cfg(not(unix))]
+        {
+            let path = lock.path().to_path_buf();
+            drop(lock);
+            fs::remove_file(path)?;
+        }
+        Ok(fs::remove_dir(&self.path)?)
+    }
+}
+
+///
+/// Returns the production ledger directories in the Aleo directory (see `aleo_dir`), sorted by path.
+///
+/// Both ledger directories named after a known network, e.g. `ledger-mainnet`,
+/// and named after a network ID, e.g. `ledger-0`, are returned.
+///
+pub fn production_ledgers() -> io::Result<Vec<LedgerInfo>> {
+    production_ledger_dirs()?.into_iter().map(LedgerDir::measure).collect()
+}
+
+///
+/// Returns the development ledger directories in the given root directory, e.g. `.ledger-{network}-{id}`,
+/// sorted by path. The root directory is not searched recursively.
+///
+pub fn development_ledgers<P: AsRef<Path>>(root: P) -> io::Result<Vec<LedgerInfo>> {
+    development_ledger_dirs(root.as_ref())?
+        .into_iter()
+        .map(LedgerDir::measure)
+        .collect()
+}
+
+/// A ledger directory found on disk, which has not been measured yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LedgerDir {
+    /// The ledger directory.
+    pub(crate) path: PathBuf,
+    /// The network of the ledger.
+    pub(crate) network: Network,
+    /// The development ID of the ledger, or `None` for a production ledger.
+    pub(crate) dev: Option<u16>,
+}
+
+impl LedgerDir {
+    /// Measures the size of the ledger directory, and the last time a file in it was modified.
+    fn measure(self) -> io::Result<LedgerInfo> {
+        let (size, modified) = dir_usage(&self.path)?;
+        Ok(LedgerInfo {
+            path: self.path,
+            network: self.network,
+            dev: self.dev,
+            size,
+            modified,
+        })
+    }
+}
+
+/// Returns the production ledger directories, as given by `production_ledgers`, without measuring them.
+pub(crate) fn production_ledger_dirs() -> io::Result<Vec<LedgerDir>> {
+    find_ledger_dirs(&aleo_dir().join("storage"), |name| {
+        let network = name.strip_prefix("ledger-")?.parse().ok()?;
+        Some((network, None))
+    })
+}
+
+/// Returns the development ledger directories, as given by `development_ledgers`, without measuring them.
+pub(crate) fn development_ledger_dirs(root: &Path) -> io::Result<Vec<LedgerDir>> {
+    find_ledger_dirs(root, |name| {
+        let (network, id) = name.strip_prefix(".ledger-")?.rsplit_once('-')?;
+        Some((network.parse().ok()?, Some(id.parse().ok()?)))
+    })
+}
+
+/// Returns the ledger directories in the given directory, whose names are parsed into their network and development ID.
+fn find_ledger_dirs(dir: &Path, parse: impl Fn(&str) -> Option<(Network, Option<u16>)>) -> io::Result<Vec<LedgerDir>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut ledgers = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let Some((network, dev)) = entry.file_name().to_str().and_then(&parse) else {
+            continue;
+        };
+        if entry.file_type()?.is_dir() {
+            ledgers.push(LedgerDir {
+                path: entry.path(),
+                network,
+                dev,
+            });
+        }
+    }
+    ledgers.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(ledgers)
+}
+
+/// Returns the total size of the files in the given directory, and the last time one of them was modified.
+pub(crate) fn dir_usage(dir: &Path) -> io::Result<(u64, Option<SystemTime>)> {
+    let (mut size, mut modified) = (0, fs::metadata(dir)?.modified().ok());
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+            modified = modified.max(metadata.modified().ok());
+        }
+    }
+    Ok((size, modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StorageLayout, StorageMode, scoped_aleo_root};
+
+    #[test]
+    fn test_development_ledgers() {
+        let root = tempfile::TempDir::new().unwrap();
+        fs::create_dir(root.path().join(".ledger-testnet-0")).unwrap();
+        fs::write(root.path().join(".ledger-testnet-0").join("CURRENT"), b"12345").unwrap();
+        fs::create_dir(root.path().join(".ledger-0-1")).unwrap();
+        fs::create_dir(root.path().join(".bft-testnet-0")).unwrap();
+        fs::write(root.path().join(".node-testnet-0.key"), b"APrivateKey1").unwrap();
+        fs::create_dir(root.path().join(".ledger-unknown-1")).unwrap();
+        fs::write(root.path().join(".ledger-mainnet-2"), b"").unwrap();
+
+        let ledgers = development_ledgers(root.path()).unwrap();
+        assert_eq!(ledgers.len(), 2);
+        assert_eq!(
+            (ledgers[0].network, ledgers[0].dev, ledgers[0].size),
+            (Network::MAINNET, Some(1), 0)
+        );
+        assert_eq!(
+            (ledgers[1].network, ledgers[1].dev, ledgers[1].size),
+            (Network::TESTNET, Some(0), 5)
+        );
+        assert!(ledgers[1].modified.is_some());
+
+        // Removing a development ledger removes its resources, but its node key.
+        ledgers[1].remove().unwrap();
+        assert!(!root.path().join(".ledger-testnet-0").exists());
+        assert!(!root.path().join(".bft-testnet-0").exists());
+        assert_eq!(
+            fs::read(root.path().join(".node-testnet-0.key")).unwrap(),
+            b"APrivateKey1"
+        );
+        assert_eq!(development_ledgers(root.path()).unwrap().len(), 1);
+
+        // Purging it removes its node key as well.
+        fs::create_dir(root.path().join(".ledger-testnet-0")).unwrap();
+        development_ledgers(root.path()).unwrap()[1].purge().unwrap();
+        assert!(!root.path().join(".node-testnet-0.key").exists());
+        assert_eq!(development_ledgers(root.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_production_ledgers() {
+        let root = tempfile::TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(root.path());
+        assert!(production_ledgers().unwrap().is_empty());
+
+        let layout = StorageLayout::new(Network::CANARY, StorageMode::Production);
+        fs::create_dir_all(layout.ledger_dir()).unwrap();
+        fs::create_dir_all(layout.bft_dir()).unwrap();
+        let ledgers = production_ledgers().unwrap();
+        assert_eq!(ledgers.len(), 1);
+        assert_eq!((ledgers[0].network, ledgers[0].dev), (Network::CANARY, None));
+
+        // A ledger in use is not removed.
+        let lock = LedgerDirLock::acquire(Network::CANARY, &StorageMode::Production).unwrap();
+        assert!(matches!(ledgers[0].remove(), Err(LockError::AlreadyLocked { .. })));
+        drop(lock);
+        fs::create_dir_all(layout.snapshots_dir().join("before-v4")).unwrap();
+        ledgers[0].remove().unwrap();
+        assert!(production_ledgers().unwrap().is_empty());
+        // Along with its BFT state, but not its snapshots.
+        assert!(!layout.bft_dir().exists());
+        assert!(layout.snapshots_dir().join("before-v4").exists());
+
+        // Unless it is purged.
+        fs::create_dir_all(layout.ledger_dir()).unwrap();
+        production_ledgers().unwrap()[0].purge().unwrap();
+        assert!(!layout.snapshots_dir().exists());
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    AtomicWriteOptions,
+    KeyMaterial,
+    keys::{KEY_FILE_MODE, create_private_parent_dirs},
+    write_atomic_with,
+};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    KeyInit,
+    XChaCha20Poly1305,
+    XNonce,
+    aead::{AeadInPlace, OsRng, rand_core::RngCore},
+};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+};
+use zeroize::Zeroizing;
+
+/// The magic bytes at the start of an encrypted file.
+const MAGIC: &[u8; 8] = b"ALEO-ENC";
+
+/// The version of the encrypted file format, which uses Argon2id and XChaCha20-Poly1305.
+pub const ENCRYPTED_FILE_VERSION: u8 = 1;
+
+/// The length of the salt of the key derivation, in bytes.
+const SALT_LEN: usize = 16;
+
+/// The length of the XChaCha20-Poly1305 nonce, in bytes.
+const NONCE_LEN: usize = 24;
+
+/// The length of the XChaCha20-Poly1305 authentication tag, in bytes.
+const TAG_LEN: usize = 16;
+
+/// The length of the header, i.e. the magic bytes, the version, the KDF parameters, the salt and the nonce.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// An error that occurs when reading or writing an encrypted file.
+#[derive(Debug)]
+pub enum EncryptionError {
+    /// An I/O error occurred while accessing the encrypted file.
+    Io(io::Error),
+    /// The file is not an encrypted file, or its header is truncated.
+    InvalidHeader,
+    /// The encrypted file has a format version this library does not support.
+    UnsupportedVersion(u8),
+    /// The key derivation parameters are out of range.
+    InvalidKdfParams(String),
+    /// The contents could not be encrypted, e.g. as they are too large.
+    EncryptionFailed,
+    /// The passphrase is wrong, or the encrypted file was tampered with.
+    DecryptionFailed,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to access the encrypted file: {error}"),
+            Self::InvalidHeader => write!(f, "the file is not an encrypted Aleo file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "the encrypted file format version {version} is not supported")
+            }
+            Self::InvalidKdfParams(error) => write!(f, "invalid key derivation parameters: {error}"),
+            Self::EncryptionFailed => write!(f, "failed to encrypt the contents"),
+            Self::DecryptionFailed => write!(f, "the passphrase is wrong, or the encrypted file is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::InvalidHeader
+            | Self::UnsupportedVersion(_)
+            | Self::InvalidKdfParams(_)
+            | Self::EncryptionFailed
+            | Self::DecryptionFailed => None,
+        }
+    }
+}
+
+impl From<io::Error> for EncryptionError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+///
+/// The parameters of the Argon2id key derivation, which turns a passphrase into an encryption key.
+///
+/// The parameters are recorded in the header of every encrypted file, so they may be raised over time
+/// without breaking existing files.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// The memory cost, in KiB.
+    pub memory_kib: u32,
+    /// The number of passes over the memory.
+    pub iterations: u32,
+    /// The degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// Returns 64 MiB of memory, 3 passes and a parallelism of 1.
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    ///
+    /// The highest parameters accepted, i.e. four times the default ones.
+    ///
+    /// As the parameters of an encrypted file are read from its header, higher ones are rejected before deriving the key,
+    /// so that a crafted file cannot make its decryption exhaust the memory or time of the node.
+    ///
+    pub const MAX: Self = Self {
+        memory_kib: 4 * 64 * 1024,
+        iterations: 4 * 3,
+        parallelism: 4,
+    };
+
+    /// Derives the encryption key from the given passphrase and salt.
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, EncryptionError> {
+        for (name, value, max) in [
+            ("memory cost", self.memory_kib, Self::MAX.memory_kib),
+            ("number of iterations", self.iterations, Self::MAX.iterations),
+            ("parallelism", self.parallelism, Self::MAX.parallelism),
+        ] {
+            if value > max {
+                return Err(EncryptionError::InvalidKdfParams(format!(
+                    "the {name} {value} exceeds the maximum of {max}"
+                )));
+            }
+        }
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|error| EncryptionError::InvalidKdfParams(error.to_string()))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase, salt, key.as_mut())
+            .map_err(|error| EncryptionError::InvalidKdfParams(error.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// The header of an encrypted file, which is authenticated along with the ciphertext.
+struct Header {
+    /// The key derivation parameters.
+    params: KdfParams,
+    /// The salt of the key derivation.
+    salt: [u8; SALT_LEN],
+    /// The nonce of the encryption.
+    nonce: [u8; NONCE_LEN],
+}
+
+impl Header {
+    /// Initializes a header with the given parameters, and a random salt and nonce.
+    fn random(params: KdfParams) -> Self {
+        let (mut salt, mut nonce) = ([0u8; SALT_LEN], [0u8; NONCE_LEN]);
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        Self { params, salt, nonce }
+    }
+
+    /// Returns the encoded header.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(ENCRYPTED_FILE_VERSION);
+        bytes.extend_from_slice(&self.params.memory_kib.to_le_bytes());
+        bytes.extend_from_slice(&self.params.iterations.to_le_bytes());
+        bytes.extend_from_slice(&self.params.parallelism.to_le_bytes());
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes
+    }
+
+    /// Decodes the header at the start of the given encrypted file.
+    fn parse(bytes: &[u8]) -> Result<Self, EncryptionError> {
+        if bytes.len() < MAGIC.len() + 1 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(EncryptionError::InvalidHeader);
+        }
+        match bytes[MAGIC.len()] {
+            ENCRYPTED_FILE_VERSION => (),
+            version => return Err(EncryptionError::UnsupportedVersion(version)),
+        }
+        let bytes = bytes
+            .get(MAGIC.len() + 1..HEADER_LEN)
+            .ok_or(EncryptionError::InvalidHeader)?;
+        let (params, rest) = bytes.split_at(12);
+        let (salt, nonce) = rest.split_at(SALT_LEN);
+        let u32_at = |i: usize| u32::from_le_bytes(params[i * 4..(i + 1) * 4].try_into().unwrap());
+        Ok(Self {
+            params: KdfParams {
+                memory_kib: u32_at(0),
+                iterations: u32_at(1),
+                parallelism: u32_at(2),
+            },
+            salt: salt.try_into().unwrap(),
+            nonce: nonce.try_into().unwrap(),
+        })
+    }
+}
+
+/// Encrypts the given contents under the given passphrase, into an encrypted file with the given header.
+fn encrypt(contents: &[u8], passphrase: &[u8], header: &Header) -> Result<Vec<u8>, EncryptionError> {
+    let key = header.params.derive_key(passphrase, &header.salt)?;
+    let header = header.to_bytes();
+    // The contents are encrypted in place, in a buffer that is never grown, so the plaintext is not left behind.
+    let mut buffer = Zeroizing::new(Vec::with_capacity(contents.len() + TAG_LEN));
+    buffer.extend_from_slice(contents);
+    let nonce = XNonce::from_slice(&header[HEADER_LEN - NONCE_LEN..]);
+    XChaCha20Poly1305::new(key.as_ref().into())
+        .encrypt_in_place(nonce, &header, &mut *buffer)
+        .map_err(|_| EncryptionError::EncryptionFailed)?;
+    Ok([header.as_slice(), buffer.as_slice()].concat())
+}
+
+/// Decrypts the given encrypted file under the given passphrase, returning its header and contents.
+fn decrypt(bytes: &[u8], passphrase: &[u8]) -> Result<(Header, KeyMaterial), EncryptionError> {
+    let header = Header::parse(bytes)?;
+    let key = header.params.derive_key(passphrase, &header.salt)?;
+    let mut buffer = Zeroizing::new(bytes[HEADER_LEN..].to_vec());
+    XChaCha20Poly1305::new(key.as_ref().into())
+        .decrypt_in_place(XNonce::from_slice(&header.nonce), &bytes[..HEADER_LEN], &mut *buffer)
+        .map_err(|_| EncryptionError::DecryptionFailed)?;
+    Ok((header, KeyMaterial(buffer)))
+}
+
+///
+/// Encrypts the given contents under the given passphrase, and writes them to the given file,
+/// as given by `write_encrypted_with` with the default key derivation parameters.
+///
+pub fn write_encrypted<P: AsRef<Path>, C: AsRef<[u8]>, K: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+    passphrase: K,
+) -> Result<(), EncryptionError> {
+    write_encrypted_with(path, contents, passphrase, &KdfParams::default())
+}
+
+///
+/// Encrypts the given contents under the given passphrase, and writes them to the given file.
+///
+/// The contents are encrypted with XChaCha20-Poly1305, under a key derived from the passphrase with Argon2id,
+/// and a fresh random salt and nonce. Like a key file (see `KeyFile`), the file is written atomically
+/// with owner-only permissions, and its missing parent directories are created with owner-only permissions.
+///
+pub fn write_encrypted_with<P: AsRef<Path>, C: AsRef<[u8]>, K: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+    passphrase: K,
+    params: &KdfParams,
+) -> Result<(), EncryptionError> {
+    let encrypted = encrypt(contents.as_ref(), passphrase.as_ref(), &Header::random(*params))?;
+    write_encrypted_bytes(path.as_ref(), &encrypted)
+}
+
+///
+/// Reads and decrypts the given encrypted file under the given passphrase.
+///
+/// Returns `EncryptionError::DecryptionFailed` if the passphrase is wrong or the file was tampered with.
+///
+pub fn read_encrypted<P: AsRef<Path>, K: AsRef<[u8]>>(path: P, passphrase: K) -> Result<KeyMaterial, EncryptionError> {
+    let bytes = fs::read(path)?;
+    Ok(decrypt(&bytes, passphrase.as_ref())?.1)
+}
+
+///
+/// Re-encrypts the given encrypted file under a new passphrase, atomically replacing it.
+///
+/// The file keeps its key derivation parameters, unless new ones are given, and gets a fresh salt and nonce.
+/// The file is left unchanged if the old passphrase is wrong.
+///
+pub fn rotate_passphrase<P: AsRef<Path>, K: AsRef<[u8]>, N: AsRef<[u8]>>(
+    path: P,
+    old_passphrase: K,
+    new_passphrase: N,
+    params: Option<&KdfParams>,
+) -> Result<(), EncryptionError> {
+    let path = path.as_ref();
+    let (header, contents) = decrypt(&fs::read(path)?, old_passphrase.as_ref())?;
+    let header = Header::random(params.copied().unwrap_or(header.params));
+    let encrypted = encrypt(&contents, new_passphrase.as_ref(), &header)?;
+    write_encrypted_bytes(path, &encrypted)
+}
+
+///
+/// Returns `true` if the given file starts with the header of an encrypted file.
+///
+/// This does not check whether the file may be decrypted, or whether its format version is supported.
+///
+pub fn is_encrypted<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let mut magic = [0u8; MAGIC.len()];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// Writes the given encrypted file atomically, with owner-only permissions.
+fn write_encrypted_bytes(path: &Path, encrypted: &[u8]) -> Result<(), EncryptionError> {
+    create_private_parent_dirs(path)?;
+    let options = AtomicWriteOptions {
+        mode: Some(KEY_FILE_MODE),
+        ..Default::default()
+    };
+    Ok(write_atomic_with(path, encrypted, &options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cheapest key derivation parameters, to keep the tests fast.
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_encrypted_file() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let path = tempdir.path().join("keys").join("validator.key.enc");
+        write_encrypted_with(&path, b"APrivateKey1", "hunter2", &TEST_PARAMS).unwrap();
+        assert!(is_encrypted(&path).unwrap());
+        assert!(
+            !fs::read(&path)
+                .unwrap()
+                .windows(12)
+                .any(|window| window == b"APrivateKey1")
+        );
+        assert_eq!(&*read_encrypted(&path, "hunter2").unwrap(), b"APrivateKey1");
+        assert!(matches!(
+            read_encrypted(&path, "hunter3"),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+
+        // Tampering with the header, e.g. with the key derivation parameters, is detected.
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[MAGIC.len() + 1] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            read_encrypted(&path, "hunter2"),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+
+        // Key derivation parameters beyond the maximum are rejected before deriving the key.
+        bytes[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            read_encrypted(&path, "hunter2"),
+            Err(EncryptionError::InvalidKdfParams(_))
+        ));
+        let params = KdfParams {
+            parallelism: KdfParams::MAX.parallelism + 1,
+            ..TEST_PARAMS
+        };
+        assert!(matches!(
+            write_encrypted_with(&path, b"APrivateKey1", "hunter2", &params),
+            Err(EncryptionError::InvalidKdfParams(_))
+        ));
+
+        bytes[MAGIC.len()] = 2;
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            read_encrypted(&path, "hunter2"),
+            Err(EncryptionError::UnsupportedVersion(2))
+        ));
+
+        fs::write(&path, b"APrivateKey1").unwrap();
+        assert!(!is_encrypted(&path).unwrap());
+        assert!(matches!(
+            read_encrypted(&path, "hunter2"),
+            Err(EncryptionError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn test_rotate_passphrase() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let path = tempdir.path().join("peers.enc");
+        write_encrypted_with(&path, b"127.0.0.1:4130", "old", &TEST_PARAMS).unwrap();
+
+        // A wrong passphrase leaves the file unchanged.
+        let before = fs::read(&path).unwrap();
+        assert!(matches!(
+            rotate_passphrase(&path, "wrong", "new", None),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        rotate_passphrase(&path, "old", "new", None).unwrap();
+        assert!(matches!(
+            read_encrypted(&path, "old"),
+            Err(EncryptionError::DecryptionFailed)
+        ));
+        assert_eq!(&*read_encrypted(&path, "new").unwrap(), b"127.0.0.1:4130");
+        assert_eq!(Header::parse(&fs::read(&path).unwrap()).unwrap().params, TEST_PARAMS);
+
+        // The key derivation parameters may be raised while rotating.
+        let params = KdfParams {
+            iterations: 2,
+            ..TEST_PARAMS
+        };
+        rotate_passphrase(&path, "new", "newer", Some(&params)).unwrap();
+        assert_eq!(Header::parse(&fs::read(&path).unwrap()).unwrap().params, params);
+        assert_eq!(&*read_encrypted(&path, "newer").unwrap(), b"127.0.0.1:4130");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encrypted_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let path = tempdir.path().join("validator.key.enc");
+        write_encrypted_with(&path, b"secret", "hunter2", &TEST_PARAMS).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, KEY_FILE_MODE);
+    }
+}
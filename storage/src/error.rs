@@ -0,0 +1,179 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// An error that occurs when resolving a directory in Aleo storage.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The home directory of the current user could not be determined.
+    MissingHomeDirectory,
+    /// The current working directory could not be determined.
+    MissingCurrentDirectory(io::Error),
+    /// The `ALEO_DIR_LAYOUT` environment variable names no layout policy (see `LayoutPolicy`).
+    InvalidLayoutPolicy(String),
+    /// The test storage mode has no temporary directory, or it no longer exists.
+    InvalidTestDirectory,
+    /// The path is not valid UTF-8.
+    NonUtf8Path(PathBuf),
+    /// The path may not be accessed or created by the current user.
+    PermissionDenied(PathBuf),
+    /// The path, or one of its ancestors, is not a directory.
+    NotADirectory(PathBuf),
+    /// An I/O error occurred while inspecting the path.
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHomeDirectory => write!(f, "the home directory of the current user could not be determined"),
+            Self::MissingCurrentDirectory(error) => {
+                write!(f, "the current working directory could not be determined: {error}")
+            }
+            Self::InvalidLayoutPolicy(policy) => {
+                write!(
+                    f,
+                    "unknown directory layout policy '{policy}', expected 'legacy', 'xdg' or 'detect'"
+                )
+            }
+            Self::InvalidTestDirectory => {
+                write!(
+                    f,
+                    "the test storage mode has no temporary directory, or it no longer exists"
+                )
+            }
+            Self::NonUtf8Path(path) => write!(f, "the path {} is not valid UTF-8", path.display()),
+            Self::PermissionDenied(path) => write!(f, "permission denied for {}", path.display()),
+            Self::NotADirectory(path) => write!(f, "{} is not a directory", path.display()),
+            Self::Io(path, error) => write!(f, "failed to access {}: {error}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::MissingCurrentDirectory(error) | Self::Io(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<StorageError> for io::Error {
+    fn from(error: StorageError) -> Self {
+        let kind = match &error {
+            StorageError::MissingHomeDirectory | StorageError::InvalidTestDirectory => io::ErrorKind::NotFound,
+            StorageError::MissingCurrentDirectory(error) | StorageError::Io(_, error) => error.kind(),
+            StorageError::NonUtf8Path(_) | StorageError::InvalidLayoutPolicy(_) => io::ErrorKind::InvalidInput,
+            StorageError::PermissionDenied(_) => io::ErrorKind::PermissionDenied,
+            StorageError::NotADirectory(_) => io::ErrorKind::NotADirectory,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+/// An error that occurs when parsing a `StorageMode` from a string.
+#[derive(Debug)]
+pub enum ParseStorageModeError {
+    /// The string is not one of `production`, `dev:{id}`, `custom:{path}` or `test`.
+    Invalid(String),
+    /// The temporary directory of a test mode could not be created.
+    TempDir(io::Error),
+    /// The path of a custom mode is empty or not valid UTF-8.
+    InvalidPath(PathBuf),
+}
+
+impl fmt::Display for ParseStorageModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(s) => write!(
+                f,
+                "invalid storage mode '{s}', expected 'production', 'dev:<id>', 'custom:<path>' or 'test'"
+            ),
+            Self::TempDir(error) => write!(f, "failed to create a temporary directory for the test mode: {error}"),
+            Self::InvalidPath(path) => {
+                write!(f, "the custom path '{}' is empty or not valid UTF-8", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseStorageModeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Invalid(_) | Self::InvalidPath(_) => None,
+            Self::TempDir(error) => Some(error),
+        }
+    }
+}
+
+///
+/// Checks that the given path may be used as a directory in Aleo storage.
+///
+/// The path must be valid UTF-8 and must either be a writable directory, or be creatable,
+/// i.e. its nearest existing ancestor must be a writable directory.
+///
+pub(crate) fn validate_dir(path: &Path) -> Result<(), StorageError> {
+    if path.to_str().is_none() {
+        return Err(StorageError::NonUtf8Path(path.to_path_buf()));
+    }
+    // Find the nearest existing ancestor, which is the path itself if it exists.
+    let mut existing = path;
+    loop {
+        match fs::metadata(existing) {
+            Ok(metadata) if metadata.is_dir() => break,
+            Ok(_) => return Err(StorageError::NotADirectory(existing.to_path_buf())),
+            // A missing path, or one beneath a file, is checked against its parent.
+            Err(error) if matches!(error.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) => {
+                match existing.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => existing = parent,
+                    // A relative path is resolved against the current working directory.
+                    Some(_) if existing != Path::new(".") => existing = Path::new("."),
+                    _ => return Ok(()),
+                }
+            }
+            Err(error) if error.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(StorageError::PermissionDenied(existing.to_path_buf()));
+            }
+            Err(error) => return Err(StorageError::Io(existing.to_path_buf(), error)),
+        }
+    }
+    if !is_writable(existing) {
+        return Err(StorageError::PermissionDenied(existing.to_path_buf()));
+    }
+    Ok(())
+}
+
+///
+/// Returns `true` if the current user may create entries in the given directory,
+/// by creating and removing a probe file in it.
+///
+/// Unlike checking the permission bits, this accounts for read-only mounts, ACLs and platforms
+/// whose permissions do not apply to directories, at the cost of briefly writing to the directory.
+///
+pub(crate) fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".aleo-probe-{}", std::process::id()));
+    match fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
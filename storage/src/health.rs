@@ -0,0 +1,482 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    LEDGER_LOCK_FILE,
+    LedgerDirLock,
+    LockError,
+    Network,
+    StorageMode,
+    aleo_network_ledger_dir,
+    atomic::temp_file_pid,
+    lock::is_process_alive,
+    snapshot::is_restore_dir,
+};
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The names of the files that may be empty in a healthy ledger directory, such as the RocksDB lock file and info log.
+const EMPTY_FILE_NAMES: &[&str] = &["LOCK", "LOG"];
+
+/// The extension of the files that may be empty in a healthy ledger directory, i.e. a RocksDB write-ahead log,
+/// which is empty until the first write after it is created.
+const EMPTY_FILE_EXTENSION: &str = "log";
+
+/// A kind of problem found in a ledger directory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(rename_all = "snake_case"))]
+pub enum HealthIssueKind {
+    /// A temporary file left behind by an interrupted atomic write (see `write_atomic`).
+    StaleTempFile,
+    /// An empty file, which is likely truncated, other than those RocksDB leaves empty, such as a new write-ahead log.
+    EmptyFile,
+    /// A file or directory that its owner may not read and write (or enter), or that anyone may write.
+    WrongPermissions {
+        /// The Unix permissions of the entry.
+        mode: u32,
+    },
+    /// A symbolic link whose target does not exist.
+    DanglingSymlink,
+    /// A directory of an interrupted snapshot or archive restore, which is recovered once the ledger directory is locked,
+    /// e.g. to repair it (see `SnapshotStore::restore`).
+    InterruptedRestore,
+    /// A directory that may not be read, whose entries are therefore not checked.
+    UnreadableDir,
+}
+
+impl HealthIssueKind {
+    /// Returns `true` if the issue may be repaired without risking the loss of data.
+    pub const fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            Self::StaleTempFile | Self::WrongPermissions { .. } | Self::InterruptedRestore
+        )
+    }
+}
+
+impl fmt::Display for HealthIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::StaleTempFile => "stale_temp_file",
+            Self::EmptyFile => "empty_file",
+            Self::WrongPermissions { .. } => "wrong_permissions",
+            Self::DanglingSymlink => "dangling_symlink",
+            Self::InterruptedRestore => "interrupted_restore",
+            Self::UnreadableDir => "unreadable_dir",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// An error that prevents a ledger directory from being checked.
+#[derive(Debug)]
+pub enum HealthError {
+    /// The ledger directory could not be locked for repairs.
+    Lock(LockError),
+    /// The ledger directory does not exist, or is not a directory.
+    NotADirectory(PathBuf),
+    /// An I/O error occurred while checking an entry of the ledger directory.
+    Io {
+        /// The path of the entry.
+        path: PathBuf,
+        /// The underlying error.
+        error: io::Error,
+    },
+}
+
+impl fmt::Display for HealthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lock(error) => write!(f, "{error}"),
+            Self::NotADirectory(path) => write!(f, "the ledger directory {} does not exist", path.display()),
+            Self::Io { path, error } => write!(f, "failed to check {}: {error}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for HealthError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Lock(error) => Some(error),
+            Self::Io { error, .. } => Some(error),
+            Self::NotADirectory(_) => None,
+        }
+    }
+}
+
+impl From<LockError> for HealthError {
+    fn from(error: LockError) -> Self {
+        Self::Lock(error)
+    }
+}
+
+/// A problem found in a ledger directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HealthIssue {
+    /// The kind of problem.
+    pub kind: HealthIssueKind,
+    /// The path of the affected entry.
+    pub path: PathBuf,
+    /// Whether the problem was repaired.
+    pub repaired: bool,
+}
+
+///
+/// The problems found in a ledger directory by a `HealthCheck`.
+///
+/// With the `serde` feature, the report may be serialized, e.g. as JSON. A path that is not valid UTF-8
+/// fails to serialize.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HealthReport {
+    /// The ledger directory.
+    pub ledger_dir: PathBuf,
+    /// The number of entries checked, excluding the ledger directory itself.
+    pub entries_checked: u64,
+    /// The problems found, sorted by path.
+    pub issues: Vec<HealthIssue>,
+}
+
+impl HealthReport {
+    /// Returns `true` if every problem found was repaired.
+    pub fn is_healthy(&self) -> bool {
+        self.issues.iter().all(|issue| issue.repaired)
+    }
+
+    /// Returns the problems that were not repaired.
+    pub fn unrepaired(&self) -> impl Iterator<Item = &HealthIssue> {
+        self.issues.iter().filter(|issue| !issue.repaired)
+    }
+}
+
+///
+/// A health check of a ledger directory, which finds the problems left behind by a crash or power loss,
+/// such as stale temporary files, empty files, wrong permissions, dangling symbolic links and interrupted restores.
+///
+/// When repairing, the ledger directory is locked, and only the problems that may be repaired without risking
+/// the loss of data are repaired: stale temporary files are removed, and permissions are fixed.
+/// The other problems are reported for an operator to investigate.
+///
+#[derive(Clone, Debug)]
+pub struct HealthCheck {
+    /// The ledger directory.
+    ledger_dir: PathBuf,
+    /// Whether to repair the problems that may be repaired safely.
+    repair: bool,
+}
+
+impl HealthCheck {
+    /// Initializes a health check of the ledger directory of the given network and storage mode.
+    pub fn new(network: Network, mode: &StorageMode) -> Self {
+        Self::for_dir(aleo_network_ledger_dir(network, mode))
+    }
+
+    /// Initializes a health check of the given ledger directory.
+    pub fn for_dir<P: Into<PathBuf>>(ledger_dir: P) -> Self {
+        Self {
+            ledger_dir: ledger_dir.into(),
+            repair: false,
+        }
+    }
+
+    /// Sets whether to repair the problems that may be repaired safely.
+    pub fn repair(mut self, repair: bool) -> Self {
+        self.repair = repair;
+        self
+    }
+
+    ///
+    /// Checks the ledger directory, repairing the problems that may be repaired safely if requested.
+    ///
+    /// Returns `HealthError::Lock` if repairing while the ledger directory is in use.
+    ///
+    pub fn run(&self) -> Result<HealthReport, HealthError> {
+        if !self.ledger_dir.is_dir() {
+            return Err(HealthError::NotADirectory(self.ledger_dir.clone()));
+        }
+        let _lock = match self.repair {
+            true => Some(LedgerDirLock::acquire_dir(&self.ledger_dir)?),
+            false => None,
+        };
+        let mut report = HealthReport {
+            ledger_dir: self.ledger_dir.clone(),
+            ..Default::default()
+        };
+        self.check_permissions(&self.ledger_dir, &mut report)?;
+        self.check_dir(&self.ledger_dir, &mut report)?;
+        report.issues.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+
+    /// Checks the entries of the given directory, and everything beneath them.
+    fn check_dir(&self, dir: &Path, report: &mut HealthReport) -> Result<(), HealthError> {
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |error| HealthError::Io { path, error }
+        };
+        // A directory that may not be read, e.g. as its owner may not enter it, is reported rather than checked.
+        let Ok(entries) = fs::read_dir(dir) else {
+            report
+                .issues
+                .push(issue(HealthIssueKind::UnreadableDir, dir.to_path_buf(), false));
+            return Ok(());
+        };
+        for entry in entries {
+            let entry = entry.map_err(io_error(dir))?;
+            let path = entry.path();
+            if dir == self.ledger_dir && entry.file_name() == LEDGER_LOCK_FILE {
+                continue;
+            }
+            report.entries_checked += 1;
+            let file_type = entry.file_type().map_err(io_error(&path))?;
+            if file_type.is_symlink() {
+                if fs::metadata(&path).is_err_and(|error| error.kind() == io::ErrorKind::NotFound) {
+                    report.issues.push(issue(HealthIssueKind::DanglingSymlink, path, false));
+                }
+                continue;
+            }
+
+            self.check_permissions(&path, report)?;
+            if dir == self.ledger_dir && is_restore_dir(&entry.file_name()) {
+                report
+                    .issues
+                    .push(issue(HealthIssueKind::InterruptedRestore, path, false));
+            } else if file_type.is_dir() {
+                self.check_dir(&path, report)?;
+            } else if let Some(pid) = temp_file_pid(&entry.file_name()) {
+                // The temporary files of a running process are still being written.
+                if pid != std::process::id() && !is_process_alive(pid) {
+                    let repaired = self.repair && fs::remove_file(&path).is_ok();
+                    report
+                        .issues
+                        .push(issue(HealthIssueKind::StaleTempFile, path, repaired));
+                }
+            } else if entry.metadata().map_err(io_error(&path))?.len() == 0 && !may_be_empty(&path) {
+                report.issues.push(issue(HealthIssueKind::EmptyFile, path, false));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the owner may read and write (or enter) the given entry, and that nobody else may write it.
+    #[cfg(unix)]
+    fn check_permissions(&self, path: &Path, report: &mut HealthReport) -> Result<(), HealthError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = fs::symlink_metadata(path).map_err(|error| HealthError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let mode = metadata.permissions().mode() & 0o777;
+        let owner_mode = if metadata.is_dir() { 0o700 } else { 0o600 };
+        let expected = (mode | owner_mode) & !0o002;
+        if mode != expected {
+            let repaired = self.repair && fs::set_permissions(path, fs::Permissions::from_mode(expected)).is_ok();
+            report.issues.push(issue(
+                HealthIssueKind::WrongPermissions { mode },
+                path.to_path_buf(),
+                repaired,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the permissions of the given entry, which are only checked on Unix.
+    #[cfg(not(unix))]
+    fn check_permissions(&self, _path: &Path, _report: &mut HealthReport) -> Result<(), HealthError> {
+        Ok(())
+    }
+}
+
+/// Returns `true` if the given file may be empty in a healthy ledger directory, e.g. a new RocksDB write-ahead log.
+fn may_be_empty(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| EMPTY_FILE_NAMES.iter().any(|empty| name == *empty))
+        || path
+            .extension()
+            .is_some_and(|extension| extension == EMPTY_FILE_EXTENSION)
+}
+
+/// Returns a problem of the given kind.
+fn issue(kind: HealthIssueKind, path: PathBuf, repaired: bool) -> HealthIssue {
+    HealthIssue { kind, path, repaired }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{atomic::TEMP_FILE_MARKER, snapshot::RESTORE_STAGING_DIR};
+
+    /// Fabricates a ledger directory with one problem of every kind, returning the paths of the broken entries.
+    fn fabricate_broken_ledger(dir: &Path) -> Vec<PathBuf> {
+        let blocks = dir.join("blocks");
+        fs::create_dir(&blocks).unwrap();
+        fs::write(blocks.join("000001.sst"), b"block").unwrap();
+        fs::write(dir.join("LOCK"), b"").unwrap();
+
+        let stale = blocks.join(format!(".000002.sst{TEMP_FILE_MARKER}{}-0", u32::MAX));
+        fs::write(&stale, b"partial").unwrap();
+        let live = dir.join(format!(".CURRENT{TEMP_FILE_MARKER}{}-0", std::process::id()));
+        fs::write(&live, b"partial").unwrap();
+        let empty = dir.join("MANIFEST-000001");
+        fs::write(&empty, b"").unwrap();
+        let restore = dir.join(RESTORE_STAGING_DIR);
+        fs::create_dir(&restore).unwrap();
+        let mut broken = vec![stale, empty, restore];
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let symlink = dir.join("OPTIONS");
+            std::os::unix::fs::symlink(dir.join("missing"), &symlink).unwrap();
+            fs::set_permissions(blocks.join("000001.sst"), fs::Permissions::from_mode(0o000)).unwrap();
+            fs::set_permissions(&blocks, fs::Permissions::from_mode(0o777)).unwrap();
+            broken.extend([symlink, blocks.join("000001.sst"), blocks]);
+        }
+        broken.sort();
+        broken
+    }
+
+    #[test]
+    fn test_health_check() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let broken = fabricate_broken_ledger(tempdir.path());
+
+        let check = HealthCheck::for_dir(tempdir.path());
+        let report = check.run().unwrap();
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.issues.iter().map(|issue| issue.path.clone()).collect::<Vec<_>>(),
+            broken
+        );
+        assert!(report.issues.iter().all(|issue| !issue.repaired));
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&report).unwrap();
+            assert_eq!(json["issues"].as_array().unwrap().len(), broken.len());
+            assert!(
+                json["issues"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|issue| issue["kind"] == "empty_file")
+            );
+        }
+
+        // Only the stale temporary files, the permissions and the interrupted restore, by locking the directory, are repaired.
+        let report = check.clone().repair(true).run().unwrap();
+        let repaired: Vec<_> = report
+            .issues
+            .iter()
+            .filter(|issue| issue.repaired)
+            .map(|issue| issue.kind)
+            .collect();
+        assert!(repaired.iter().all(HealthIssueKind::is_repairable));
+        assert!(repaired.contains(&HealthIssueKind::StaleTempFile));
+
+        let report = check.run().unwrap();
+        let kinds: Vec<_> = report.issues.iter().map(|issue| issue.kind).collect();
+        let mut expected = vec![HealthIssueKind::EmptyFile];
+        #[cfg(unix)]
+        expected.push(HealthIssueKind::DanglingSymlink);
+        assert_eq!(kinds, expected);
+    }
+
+    #[test]
+    fn test_health_check_rocksdb() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let dir = tempdir.path();
+
+        // A healthy RocksDB directory just after it was opened, whose lock file and write-ahead log are empty.
+        fs::write(dir.join("CURRENT"), b"MANIFEST-000005\n").unwrap();
+        fs::write(dir.join("IDENTITY"), b"9f2c4b1e-8d5a-4c3e-b7f6-1a2b3c4d5e6f").unwrap();
+        fs::write(dir.join("LOCK"), b"").unwrap();
+        fs::write(dir.join("LOG"), b"RocksDB version: 8.10.0\n").unwrap();
+        fs::write(dir.join("MANIFEST-000005"), b"manifest").unwrap();
+        fs::write(dir.join("OPTIONS-000007"), b"[Version]\n").unwrap();
+        fs::write(dir.join("000004.sst"), b"block").unwrap();
+        fs::write(dir.join("000008.log"), b"").unwrap();
+
+        let report = HealthCheck::for_dir(dir).run().unwrap();
+        assert_eq!(report.issues, []);
+        assert_eq!(report.entries_checked, 8);
+
+        // Any other empty file is likely truncated.
+        fs::write(dir.join("000004.sst"), b"").unwrap();
+        let report = HealthCheck::for_dir(dir).run().unwrap();
+        assert_eq!(report.issues, [issue(
+            HealthIssueKind::EmptyFile,
+            dir.join("000004.sst"),
+            false
+        )]);
+    }
+
+    #[test]
+    fn test_health_check_locked() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        fs::write(tempdir.path().join("CURRENT"), b"MANIFEST-000001").unwrap();
+        let lock = LedgerDirLock::acquire_dir(tempdir.path()).unwrap();
+
+        // A ledger directory in use may be checked, but not repaired.
+        let report = HealthCheck::for_dir(tempdir.path()).run().unwrap();
+        assert!(report.is_healthy());
+        assert_eq!(report.entries_checked, 1);
+        assert!(matches!(
+            HealthCheck::for_dir(tempdir.path()).repair(true).run(),
+            Err(HealthError::Lock(LockError::AlreadyLocked { .. }))
+        ));
+        drop(lock);
+
+        assert!(matches!(
+            HealthCheck::for_dir(tempdir.path().join("missing")).run(),
+            Err(HealthError::NotADirectory(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_health_check_unreadable_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let blocks = tempdir.path().join("blocks");
+        fs::create_dir(&blocks).unwrap();
+        fs::write(blocks.join("000001.sst"), b"block").unwrap();
+        fs::set_permissions(&blocks, fs::Permissions::from_mode(0o000)).unwrap();
+
+        // The directory is reported, rather than failing the whole check.
+        let report = HealthCheck::for_dir(tempdir.path()).run().unwrap();
+        let kinds: Vec<_> = report.issues.iter().map(|issue| issue.kind).collect();
+        assert_eq!(kinds[0], HealthIssueKind::WrongPermissions { mode: 0o000 });
+        // The superuser may read the directory regardless of its permissions.
+        if fs::read_dir(&blocks).is_err() {
+            assert_eq!(kinds[1..], [HealthIssueKind::UnreadableDir]);
+        }
+
+        // Once its permissions are repaired, the directory is checked.
+        let report = HealthCheck::for_dir(tempdir.path()).repair(true).run().unwrap();
+        assert!(report.is_healthy());
+        assert_eq!(report.entries_checked, 2);
+    }
+}
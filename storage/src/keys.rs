@@ -0,0 +1,268 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{AtomicWriteOptions, StorageLayout, write_atomic_with};
+
+use std::{
+    fmt,
+    fs::{DirBuilder, File},
+    io::{self, Read},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+use zeroize::Zeroizing;
+
+/// The permissions of a key file, which is readable and writable only by its owner.
+pub(crate) const KEY_FILE_MODE: u32 = 0o600;
+
+/// The permissions of a directory created to hold a key file, which is accessible only by its owner.
+const KEY_DIR_MODE: u32 = 0o700;
+
+/// An error that occurs when reading or writing a key file.
+#[derive(Debug)]
+pub enum KeyFileError {
+    /// An I/O error occurred while accessing the key file.
+    Io(io::Error),
+    /// The key file is not a regular file.
+    NotAFile(PathBuf),
+    /// The key file is accessible by its group or other users.
+    InsecurePermissions {
+        /// The path of the key file.
+        path: PathBuf,
+        /// The Unix permissions of the key file.
+        mode: u32,
+    },
+}
+
+impl fmt::Display for KeyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to access the key file: {error}"),
+            Self::NotAFile(path) => write!(f, "the key file {} is not a regular file", path.display()),
+            Self::InsecurePermissions { path, mode } => write!(
+                f,
+                "the key file {} has permissions {mode:03o}, but must only be accessible by its owner (e.g. chmod 600)",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::NotAFile(_) | Self::InsecurePermissions { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for KeyFileError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+///
+/// The contents of a key file, which are zeroized when dropped.
+///
+/// The contents are redacted from the `Debug` output.
+///
+pub struct KeyMaterial(pub(crate) Zeroizing<Vec<u8>>);
+
+impl Deref for KeyMaterial {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for KeyMaterial {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for KeyMaterial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyMaterial([REDACTED; {}])", self.0.len())
+    }
+}
+
+///
+/// A file holding key material, such as the private key of a node or validator.
+///
+/// On Unix, a key file is written with owner-only permissions (`0o600`), and any parent directory
+/// it creates is accessible only by its owner (`0o700`), regardless of the process umask.
+/// A key file that is accessible by its group or other users is refused when read,
+/// unless `allow_insecure_permissions` is set. On other platforms, permissions are not enforced.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyFile {
+    /// The path of the key file.
+    path: PathBuf,
+    /// Whether to read the key file even if it is accessible by its group or other users.
+    allow_insecure_permissions: bool,
+}
+
+impl KeyFile {
+    /// Initializes a key file at the given path.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            allow_insecure_permissions: false,
+        }
+    }
+
+    /// Initializes the key file of the node's private key in the given storage layout.
+    pub fn node_key(layout: &StorageLayout) -> Self {
+        Self::new(layout.node_key_path())
+    }
+
+    /// Sets whether to read the key file even if it is accessible by its group or other users.
+    pub fn allow_insecure_permissions(mut self, allow: bool) -> Self {
+        self.allow_insecure_permissions = allow;
+        self
+    }
+
+    /// Returns the path of the key file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if the key file exists.
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    ///
+    /// Writes the given key material to the key file, atomically replacing any previous key.
+    ///
+    /// The missing parent directories are created with owner-only permissions,
+    /// while the permissions of the existing ones are left unchanged.
+    ///
+    pub fn write<K: AsRef<[u8]>>(&self, key: K) -> Result<(), KeyFileError> {
+        create_private_parent_dirs(&self.path)?;
+        let options = AtomicWriteOptions {
+            mode: Some(KEY_FILE_MODE),
+            ..Default::default()
+        };
+        Ok(write_atomic_with(&self.path, key, &options)?)
+    }
+
+    ///
+    /// Reads the key material from the key file.
+    ///
+    /// Returns `KeyFileError::InsecurePermissions` if the key file is accessible by its group or other users,
+    /// unless `allow_insecure_permissions` is set.
+    ///
+    pub fn read(&self) -> Result<KeyMaterial, KeyFileError> {
+        let file = File::open(&self.path)?;
+        // The metadata is read from the open file, so that it cannot be swapped after it is checked.
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Err(KeyFileError::NotAFile(self.path.clone()));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = metadata.permissions().mode() & 0o777;
+            if mode & 0o077 != 0 && !self.allow_insecure_permissions {
+                return Err(KeyFileError::InsecurePermissions {
+                    path: self.path.clone(),
+                    mode,
+                });
+            }
+        }
+
+        // The buffer is allocated upfront and never grown, so that no copy of the key is left behind unzeroized.
+        let len = metadata.len() as usize;
+        let mut key = Zeroizing::new(Vec::with_capacity(len));
+        file.take(len as u64).read_to_end(&mut key)?;
+        Ok(KeyMaterial(key))
+    }
+}
+
+/// Creates the missing parent directories of the given file, which are accessible only by their owner.
+pub(crate) fn create_private_parent_dirs(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => {
+            let mut builder = DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::DirBuilderExt;
+                builder.mode(KEY_DIR_MODE);
+            }
+            builder.create(parent)
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Network, StorageMode};
+
+    #[test]
+    fn test_key_file() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let layout = StorageLayout::new(Network::TESTNET, StorageMode::Custom(tempdir.path().join("node")));
+        let key_file = KeyFile::node_key(&layout);
+        assert!(!key_file.exists());
+        assert!(matches!(key_file.read(), Err(KeyFileError::Io(_))));
+
+        key_file.write(b"APrivateKey1").unwrap();
+        assert_eq!(&*key_file.read().unwrap(), b"APrivateKey1");
+        assert_eq!(format!("{:?}", key_file.read().unwrap()), "KeyMaterial([REDACTED; 12])");
+
+        key_file.write(b"APrivateKey2").unwrap();
+        assert_eq!(&*key_file.read().unwrap(), b"APrivateKey2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_permissions() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let key_file = KeyFile::new(tempdir.path().join("keys").join("validator").join("validator.key"));
+        key_file.write(b"secret").unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(key_file.path()), KEY_FILE_MODE);
+        assert_eq!(mode(key_file.path().parent().unwrap()), KEY_DIR_MODE);
+        assert_eq!(mode(&tempdir.path().join("keys")), KEY_DIR_MODE);
+
+        // A key file that is readable by others is refused, unless allowed.
+        fs::set_permissions(key_file.path(), fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(matches!(
+            key_file.read(),
+            Err(KeyFileError::InsecurePermissions { mode: 0o644, .. })
+        ));
+        let insecure = key_file.clone().allow_insecure_permissions(true);
+        assert_eq!(&*insecure.read().unwrap(), b"secret");
+
+        // Rewriting the key file restores its permissions.
+        key_file.write(b"secret").unwrap();
+        assert_eq!(mode(key_file.path()), KEY_FILE_MODE);
+
+        // A directory is not a key file.
+        let dir = KeyFile::new(tempdir.path().join("keys"));
+        assert!(matches!(dir.read(), Err(KeyFileError::NotAFile(_))));
+    }
+}
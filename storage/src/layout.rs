@@ -0,0 +1,407 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    Network,
+    StorageMode,
+    aleo_cache_dir,
+    aleo_config_dir,
+    aleo_data_dir,
+    aleo_network_ledger_dir,
+    aleo_state_dir,
+    remove_stale_temp_files,
+};
+
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A resource that a node keeps on disk, for a single network.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum NodeResource {
+    /// The BFT and consensus state.
+    Bft,
+    /// The node's private key.
+    NodeKey,
+    /// The list of known peers.
+    Peers,
+    /// The log files.
+    Logs,
+    /// The snapshots of the ledger.
+    Snapshots,
+    /// The node's configuration file.
+    Config,
+}
+
+impl NodeResource {
+    /// Every resource.
+    const ALL: [Self; 6] = [
+        Self::Bft,
+        Self::NodeKey,
+        Self::Peers,
+        Self::Logs,
+        Self::Snapshots,
+        Self::Config,
+    ];
+
+    /// Returns the base name of the resource.
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Bft => "bft",
+            Self::NodeKey => "node",
+            Self::Peers => "peers",
+            Self::Logs => "logs",
+            Self::Snapshots => "snapshots",
+            Self::Config => "config",
+        }
+    }
+
+    /// Returns the file extension of the resource, if it is a file.
+    const fn extension(&self) -> &'static str {
+        match self {
+            Self::NodeKey => ".key",
+            Self::Config => ".toml",
+            Self::Bft | Self::Peers | Self::Logs | Self::Snapshots => "",
+        }
+    }
+
+    /// Returns the directory holding the resource in production mode.
+    fn production_dir(&self) -> PathBuf {
+        match self {
+            Self::Bft | Self::Snapshots => aleo_data_dir().join("storage"),
+            Self::NodeKey | Self::Config => aleo_config_dir(),
+            Self::Peers | Self::Logs => aleo_state_dir(),
+        }
+    }
+}
+
+///
+/// The locations of every resource a node keeps on disk, for a given network and storage mode.
+///
+/// In production mode, the resources are spread across the Aleo directories (see `aleo_dir`),
+/// e.g. `~/.aleo/storage/ledger-{network}` and `~/.aleo/storage/bft-{network}`.
+/// In development mode, the resources are hidden siblings of the ledger, e.g. `.ledger-{network}-{id}`
+/// and `.bft-{network}-{id}`, while the proving and verifying keys are shared with production mode.
+/// In custom and test mode, the given directory holds the ledger, and every other resource is a hidden sibling
+/// named after it, e.g. `/path/to/.bft-custom` for `/path/to/custom`, so that nothing is shared with other nodes.
+/// As the resources of the test mode are outside of its temporary directory, they are not removed along with it,
+/// unless the directory belongs to a `TestStorage`.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageLayout {
+    /// The network.
+    network: Network,
+    /// The storage mode.
+    mode: StorageMode,
+}
+
+impl StorageLayout {
+    /// Initializes a new storage layout for the given network and storage mode.
+    pub fn new(network: Network, mode: StorageMode) -> Self {
+        Self { network, mode }
+    }
+
+    /// Returns the network.
+    pub const fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the storage mode.
+    pub const fn mode(&self) -> &StorageMode {
+        &self.mode
+    }
+
+    /// Returns the directory of the ledger, as given by `aleo_network_ledger_dir`.
+    pub fn ledger_dir(&self) -> PathBuf {
+        aleo_network_ledger_dir(self.network, &self.mode)
+    }
+
+    /// Returns the directory of the BFT and consensus state.
+    pub fn bft_dir(&self) -> PathBuf {
+        self.node_resource(NodeResource::Bft)
+    }
+
+    /// Returns the path of the node's private key.
+    pub fn node_key_path(&self) -> PathBuf {
+        self.node_resource(NodeResource::NodeKey)
+    }
+
+    /// Returns the path of the list of known peers.
+    pub fn peers_path(&self) -> PathBuf {
+        self.node_resource(NodeResource::Peers)
+    }
+
+    /// Returns the directory of the log files.
+    pub fn logs_dir(&self) -> PathBuf {
+        self.node_resource(NodeResource::Logs)
+    }
+
+    /// Returns the directory of the snapshots of the ledger (see `SnapshotStore`).
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.node_resource(NodeResource::Snapshots)
+    }
+
+    /// Returns the path of the node's configuration file (see `ConfigLoader`).
+    pub fn config_path(&self) -> PathBuf {
+        self.node_resource(NodeResource::Config)
+    }
+
+    ///
+    /// Returns the configuration files of the node, in increasing order of precedence: the base configuration file,
+    /// the configuration file of the network, and the configuration file of the storage mode, unless it is the
+    /// configuration file of the network, as in production mode.
+    ///
+    /// These are the layers loaded by a `ConfigLoader` and watched by a `StorageWatcher`.
+    ///
+    pub(crate) fn config_files(&self) -> Vec<PathBuf> {
+        let (name, extension) = (NodeResource::Config.name(), NodeResource::Config.extension());
+        let network_file = aleo_config_dir().join(format!("{name}-{}{extension}", self.network));
+        let mode_file = self.config_path();
+        let mut files = vec![
+            aleo_config_dir().join(format!("{name}{extension}")),
+            network_file.clone(),
+        ];
+        files.extend(Some(mode_file).filter(|mode_file| *mode_file != network_file));
+        files
+    }
+
+    ///
+    /// Returns the directory of the downloaded resources, such as parameters.
+    ///
+    /// In production and development mode, the expected directory path is `~/.aleo/resources`.
+    /// In custom and test mode, the expected directory path is `/path/to/.resources-custom`.
+    ///
+    pub fn resources_dir(&self) -> PathBuf {
+        match self.mode {
+            StorageMode::Production | StorageMode::Development(_) => aleo_cache_dir().join("resources"),
+            StorageMode::Custom(_) | StorageMode::Test(_) => custom_resource(&self.ledger_dir(), "resources", ""),
+        }
+    }
+
+    /// Returns the directory of the cached proving keys.
+    pub fn proving_keys_dir(&self) -> PathBuf {
+        self.resources_dir().join(format!("proving-keys-{}", self.network))
+    }
+
+    /// Returns the directory of the cached verifying keys.
+    pub fn verifying_keys_dir(&self) -> PathBuf {
+        self.resources_dir().join(format!("verifying-keys-{}", self.network))
+    }
+
+    ///
+    /// Removes the temporary files left behind by interrupted atomic writes (see `write_atomic`)
+    /// in the directories of this layout, and returns their paths.
+    ///
+    /// This is meant to be called on startup, while no other process uses the layout.
+    ///
+    pub fn remove_stale_temp_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut dirs = vec![self.ledger_dir(), self.bft_dir(), self.logs_dir(), self.resources_dir()];
+        for file in [self.node_key_path(), self.peers_path()] {
+            dirs.extend(file.parent().map(Path::to_path_buf));
+        }
+        dirs.sort();
+        dirs.dedup();
+
+        let mut removed = Vec::new();
+        for dir in dirs {
+            removed.extend(remove_stale_temp_files(dir)?);
+        }
+        Ok(removed)
+    }
+
+    /// Returns the location of the given resource, which is specific to this node.
+    fn node_resource(&self, resource: NodeResource) -> PathBuf {
+        let (name, extension) = (resource.name(), resource.extension());
+        match self.mode {
+            StorageMode::Production => resource
+                .production_dir()
+                .join(format!("{name}-{}{extension}", self.network)),
+            StorageMode::Development(id) => development_resource(&self.ledger_dir(), resource, &self.network, id),
+            StorageMode::Custom(_) | StorageMode::Test(_) => custom_resource(&self.ledger_dir(), name, extension),
+        }
+    }
+}
+
+/// Returns the resources of the given custom or test ledger directory, including its downloaded resources, which are its siblings.
+pub(crate) fn custom_resources(ledger_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = NodeResource::ALL
+        .map(|resource| custom_resource(ledger_dir, resource.name(), resource.extension()))
+        .to_vec();
+    paths.push(custom_resource(ledger_dir, "resources", ""));
+    paths
+}
+
+///
+/// Returns the resources of the given development ledger directory, which are its siblings,
+/// named after the network as the ledger directory is, i.e. its name or its ID.
+///
+pub(crate) fn development_resources(ledger_dir: &Path, network: &dyn fmt::Display, id: u16) -> Vec<PathBuf> {
+    NodeResource::ALL
+        .into_iter()
+        .map(|resource| development_resource(ledger_dir, resource, network, id))
+        .collect()
+}
+
+///
+/// Returns the resources of the given production ledger directory that are its siblings, i.e. its BFT state and snapshots,
+/// named after the network as the ledger directory is, e.g. `bft-mainnet` or `bft-0`.
+///
+pub(crate) fn production_resources(ledger_dir: &Path) -> Vec<PathBuf> {
+    [NodeResource::Bft, NodeResource::Snapshots]
+        .into_iter()
+        .filter_map(|resource| production_resource(ledger_dir, resource))
+        .collect()
+}
+
+///
+/// Returns the resources next to the given production or development ledger directory that are kept when it is removed,
+/// unless it is purged (see `LedgerInfo::purge`), i.e. its snapshots, and the node key and configuration file
+/// of a development ledger. Those of a production ledger are kept in the Aleo config directory, and never removed.
+///
+pub(crate) fn kept_resources(ledger_dir: &Path, network: Network, dev: Option<u16>) -> Vec<PathBuf> {
+    match dev {
+        Some(id) => [NodeResource::NodeKey, NodeResource::Config, NodeResource::Snapshots]
+            .into_iter()
+            .map(|resource| development_resource(ledger_dir, resource, &network, id))
+            .collect(),
+        None => production_resource(ledger_dir, NodeResource::Snapshots)
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Returns the location of the given resource of a production ledger directory `ledger-{network}`, e.g. `bft-{network}`.
+fn production_resource(ledger_dir: &Path, resource: NodeResource) -> Option<PathBuf> {
+    let network = ledger_dir.file_name()?.to_str()?.strip_prefix("ledger-")?;
+    let (name, extension) = (resource.name(), resource.extension());
+    Some(ledger_dir.with_file_name(format!("{name}-{network}{extension}")))
+}
+
+/// Returns the location of the given resource of a development ledger directory, e.g. `.bft-{network}-{id}`.
+fn development_resource(ledger_dir: &Path, resource: NodeResource, network: &dyn fmt::Display, id: u16) -> PathBuf {
+    ledger_dir.with_file_name(format!(".{}-{network}-{id}{}", resource.name(), resource.extension()))
+}
+
+/// Returns the location of the resource with the given name and extension of a custom or test ledger directory `{dir}`, e.g. `.bft-{dir}`.
+fn custom_resource(ledger_dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let mut file_name = OsString::from(format!(".{name}-"));
+    file_name.push(ledger_dir.file_name().unwrap_or(OsStr::new("ledger")));
+    file_name.push(extension);
+    ledger_dir.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoped_aleo_root;
+
+    #[test]
+    fn test_production_layout() {
+        let _guard = scoped_aleo_root("/aleo");
+        let layout = StorageLayout::new(Network::TESTNET, StorageMode::Production);
+
+        assert_eq!(layout.ledger_dir(), PathBuf::from("/aleo/storage/ledger-testnet"));
+        assert_eq!(layout.bft_dir(), PathBuf::from("/aleo/storage/bft-testnet"));
+        assert_eq!(layout.node_key_path(), PathBuf::from("/aleo/node-testnet.key"));
+        assert_eq!(layout.peers_path(), PathBuf::from("/aleo/peers-testnet"));
+        assert_eq!(layout.logs_dir(), PathBuf::from("/aleo/logs-testnet"));
+        assert_eq!(layout.snapshots_dir(), PathBuf::from("/aleo/storage/snapshots-testnet"));
+        assert_eq!(layout.config_path(), PathBuf::from("/aleo/config-testnet.toml"));
+        assert_eq!(layout.config_files(), [
+            PathBuf::from("/aleo/config.toml"),
+            PathBuf::from("/aleo/config-testnet.toml")
+        ]);
+        assert_eq!(
+            layout.proving_keys_dir(),
+            PathBuf::from("/aleo/resources/proving-keys-testnet")
+        );
+        assert_eq!(
+            layout.verifying_keys_dir(),
+            PathBuf::from("/aleo/resources/verifying-keys-testnet")
+        );
+    }
+
+    #[test]
+    fn test_development_layout() {
+        // The development root is process-wide state.
+        let _lock = crate::tests::env_lock();
+        let _guard = scoped_aleo_root("/aleo");
+        let layout = StorageLayout::new(Network::TESTNET, StorageMode::Development(3));
+        let root = layout.ledger_dir().parent().unwrap().to_path_buf();
+
+        assert_eq!(layout.ledger_dir(), root.join(".ledger-testnet-3"));
+        assert_eq!(layout.bft_dir(), root.join(".bft-testnet-3"));
+        assert_eq!(layout.node_key_path(), root.join(".node-testnet-3.key"));
+        assert_eq!(layout.peers_path(), root.join(".peers-testnet-3"));
+        assert_eq!(layout.logs_dir(), root.join(".logs-testnet-3"));
+        assert_eq!(layout.snapshots_dir(), root.join(".snapshots-testnet-3"));
+        assert_eq!(layout.config_path(), root.join(".config-testnet-3.toml"));
+        assert_eq!(layout.config_files(), [
+            PathBuf::from("/aleo/config.toml"),
+            PathBuf::from("/aleo/config-testnet.toml"),
+            root.join(".config-testnet-3.toml")
+        ]);
+        // The keys are shared with production mode.
+        assert_eq!(
+            layout.proving_keys_dir(),
+            PathBuf::from("/aleo/resources/proving-keys-testnet")
+        );
+    }
+
+    #[test]
+    fn test_custom_and_test_layout() {
+        let layout = StorageLayout::new(Network::CANARY, StorageMode::Custom("/data/node".into()));
+        assert_eq!(layout.ledger_dir(), PathBuf::from("/data/node"));
+        assert_eq!(layout.bft_dir(), PathBuf::from("/data/.bft-node"));
+        assert_eq!(layout.node_key_path(), PathBuf::from("/data/.node-node.key"));
+        assert_eq!(layout.peers_path(), PathBuf::from("/data/.peers-node"));
+        assert_eq!(layout.logs_dir(), PathBuf::from("/data/.logs-node"));
+        assert_eq!(layout.snapshots_dir(), PathBuf::from("/data/.snapshots-node"));
+        assert_eq!(layout.config_path(), PathBuf::from("/data/.config-node.toml"));
+        assert_eq!(
+            layout.proving_keys_dir(),
+            PathBuf::from("/data/.resources-node/proving-keys-canary")
+        );
+
+        // No resource is nested in the ledger directory.
+        let layout = StorageLayout::new(Network::CANARY, StorageMode::new_test(None));
+        let ledger_dir = layout.ledger_dir();
+        assert_eq!(layout.bft_dir().parent(), ledger_dir.parent());
+        assert!(!layout.verifying_keys_dir().starts_with(&ledger_dir));
+        for resource in custom_resources(&ledger_dir) {
+            assert_eq!(resource.parent(), ledger_dir.parent());
+        }
+    }
+
+    #[test]
+    fn test_remove_stale_temp_files() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let layout = StorageLayout::new(Network::CANARY, StorageMode::Custom(tempdir.path().join("node")));
+        crate::write_atomic(layout.peers_path(), b"peers").unwrap();
+
+        let stale = layout
+            .logs_dir()
+            .join(format!(".node.log{}{}-0", crate::atomic::TEMP_FILE_MARKER, u32::MAX));
+        std::fs::create_dir_all(layout.logs_dir()).unwrap();
+        std::fs::write(&stale, b"").unwrap();
+
+        assert_eq!(layout.remove_stale_temp_files().unwrap(), [stale]);
+        assert!(layout.peers_path().exists());
+    }
+}
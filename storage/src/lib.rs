@@ -14,13 +14,151 @@
 // You should have received a copy of the GNU General Public License
 // along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
 
-use dirs::home_dir;
-use std::{path::PathBuf, sync::Arc};
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "archive")]
+pub use archive::{
+    ARCHIVE_HEADER_FILE,
+    ARCHIVE_MANIFEST_FILE,
+    ArchiveEntry,
+    ArchiveError,
+    ArchiveManifest,
+    export_ledger,
+    import_ledger,
+};
+
+mod atomic;
+pub use atomic::{AtomicWriteOptions, BACKUP_EXTENSION, remove_stale_temp_files, write_atomic, write_atomic_with};
+
+mod discovery;
+pub use discovery::{LedgerInfo, development_ledgers, production_ledgers};
+
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "config")]
+pub use config::{CONFIG_ENV_PREFIX, CONFIG_FILE, ConfigEntry, ConfigError, ConfigLoader, ConfigSource, NodeConfig};
+
+mod development;
+pub use development::{
+    ALEO_DEV_ROOT_ENV,
+    DEV_ROOT_MARKER,
+    DevRootSource,
+    DevelopmentRoot,
+    clear_development_root,
+    development_root,
+    set_development_root,
+    set_development_root_discovery,
+};
+
+mod directories;
+pub use directories::{
+    ALEO_DIR_LAYOUT_ENV,
+    DirectoryLayout,
+    LayoutPolicy,
+    aleo_cache_dir,
+    aleo_config_dir,
+    aleo_data_dir,
+    aleo_state_dir,
+    clear_layout_policy,
+    detect_directory_layout,
+    directory_layout,
+    layout_policy,
+    set_layout_policy,
+};
+
+mod error;
+pub use error::{ParseStorageModeError, StorageError};
+
+#[cfg(feature = "encryption")]
+mod encryption;
+#[cfg(feature = "encryption")]
+pub use encryption::{
+    ENCRYPTED_FILE_VERSION,
+    EncryptionError,
+    KdfParams,
+    is_encrypted,
+    read_encrypted,
+    rotate_passphrase,
+    write_encrypted,
+    write_encrypted_with,
+};
+
+mod hash;
+
+mod health;
+pub use health::{HealthCheck, HealthError, HealthIssue, HealthIssueKind, HealthReport};
+
+mod keys;
+pub use keys::{KeyFile, KeyFileError, KeyMaterial};
+
+mod layout;
+pub use layout::StorageLayout;
+
+mod lock;
+pub use lock::{LEDGER_LOCK_FILE, LedgerDirLock, LockError, LockHolder};
+
+mod cache;
+pub use cache::{ArtifactCache, CacheError, CachedArtifact, Verification};
+
+#[cfg(feature = "clap")]
+mod cli;
+#[cfg(feature = "clap")]
+pub use cli::StorageModeParser;
+
+#[cfg(feature = "serde")]
+mod serialize;
+
+mod logs;
+pub use logs::{LogRotation, RotatingLog};
+
+mod manifest;
+pub use manifest::{LEDGER_MANIFEST_FILE, LedgerManifest};
+
+mod migration;
+pub use migration::{MigrationError, MigrationRegistry, MigrationStepError};
+
+mod network;
+pub use network::{Network, migrate_network_ledger_dir};
+
+mod preflight;
+pub use preflight::{LowSpaceEvent, LowSpaceWatcher, PreflightReport, preflight, preflight_dir};
+
+mod root;
+pub use root::{ALEO_HOME_ENV, AleoRootGuard, clear_aleo_root, scoped_aleo_root, set_aleo_root};
+
+mod usage;
+pub use usage::{UsageCategory, UsageEntry, UsageReport, UsageScanner, storage_usage};
+
+mod watcher;
+pub use watcher::{StorageEvent, StorageWatcher, WatchOptions};
+
+mod snapshot;
+pub use snapshot::{SNAPSHOT_METADATA_FILE, Snapshot, SnapshotError, SnapshotStore};
+
+mod test_storage;
+pub use test_storage::{
+    ALEO_KEEP_TEST_DIR_ENV,
+    ALEO_TEST_RAM_DIR_ENV,
+    FixtureMode,
+    TestStorage,
+    TestStorageBuilder,
+    TestStorageUsage,
+};
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 use tempfile::TempDir;
 
 /// The directory name for Aleo-related resources.
 const ALEO_DIRECTORY: &str = ".aleo";
 
+/// The prefix of the temporary directories created for the test mode.
+const TEST_DIR_PREFIX: &str = "aleo_storage_";
+
 /// An enum to define the operating mode of the Aleo node.
 #[derive(Clone, Debug)]
 pub enum StorageMode {
@@ -35,13 +173,38 @@ pub enum StorageMode {
 }
 
 impl StorageMode {
+    ///
+    /// Initializes the test mode with the given temporary directory, or a new one if `None`.
+    ///
+    /// See `TestStorage` to keep the directory of a failed test, name it after the test, or seed it from a fixture.
+    ///
     pub fn new_test(tempdir: Option<Arc<TempDir>>) -> Self {
         if tempdir.is_some() {
             Self::Test(tempdir)
         } else {
-            Self::Test(Some(Arc::new(tempfile::TempDir::with_prefix("aleo_storage_").unwrap())))
+            Self::Test(Some(Arc::new(tempfile::TempDir::with_prefix(TEST_DIR_PREFIX).unwrap())))
         }
     }
+
+    ///
+    /// Initializes the custom mode with the given path, which must be non-empty and valid UTF-8,
+    /// so that the mode may be formatted and parsed back, or serialized, without loss.
+    ///
+    /// Constructing `StorageMode::Custom` directly does not check the path.
+    ///
+    pub fn custom<P: Into<PathBuf>>(path: P) -> Result<Self, ParseStorageModeError> {
+        let path = path.into();
+        check_custom_path(&path)?;
+        Ok(Self::Custom(path))
+    }
+}
+
+/// Checks that the given custom path is non-empty and valid UTF-8.
+fn check_custom_path(path: &Path) -> Result<(), ParseStorageModeError> {
+    match path.to_str() {
+        Some(s) if !s.is_empty() => Ok(()),
+        _ => Err(ParseStorageModeError::InvalidPath(path.to_path_buf())),
+    }
 }
 
 impl PartialEq for StorageMode {
@@ -75,6 +238,68 @@ impl From<Arc<TempDir>> for StorageMode {
     }
 }
 
+impl FromStr for StorageMode {
+    type Err = ParseStorageModeError;
+
+    ///
+    /// Parses a storage mode from `production`, `dev:{id}`, `custom:{path}` or `test`.
+    ///
+    /// As the temporary directory of a test mode cannot be shared, `test` creates a new one.
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseStorageModeError::Invalid(s.to_string());
+        match s.split_once(':') {
+            None if s == "production" => Ok(Self::Production),
+            None if s == "test" => Ok(Self::Test(Some(Arc::new(
+                TempDir::with_prefix(TEST_DIR_PREFIX).map_err(ParseStorageModeError::TempDir)?,
+            )))),
+            Some(("dev" | "development", id)) => id.parse().map(Self::Development).map_err(|_| invalid()),
+            Some(("custom", path)) if !path.is_empty() => Ok(Self::Custom(PathBuf::from(path))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for StorageMode {
+    ///
+    /// Formats the storage mode as `production`, `dev:{id}`, `custom:{path}` or `test`.
+    ///
+    /// The temporary directory of a test mode is omitted, so parsing the output creates a new one.
+    /// An empty or non-UTF-8 custom path, which `StorageMode::custom` rejects, cannot be parsed back
+    /// and is formatted lossily; serializing such a mode fails instead.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Production => write!(f, "production"),
+            Self::Development(id) => write!(f, "dev:{id}"),
+            Self::Custom(path) => write!(f, "custom:{}", path.display()),
+            Self::Test(_) => write!(f, "test"),
+        }
+    }
+}
+
+/// The source a ledger directory was resolved from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LedgerDirSource {
+    /// The Aleo directory, as given by `aleo_dir`, in production mode.
+    AleoDir,
+    /// The development root, as given by `development_root`, in development mode.
+    DevelopmentRoot(DevRootSource),
+    /// The directory given in custom mode.
+    Custom,
+    /// The temporary directory of the test mode.
+    Test,
+}
+
+/// A ledger directory, along with the source it was resolved from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedLedgerDir {
+    /// The ledger directory.
+    pub path: PathBuf,
+    /// The source of the ledger directory.
+    pub source: LedgerDirSource,
+}
+
 impl StorageMode {
     /// Returns the development ID if the mode is development.
     pub const fn dev(&self) -> Option<u16> {
@@ -89,60 +314,147 @@ impl StorageMode {
 /// Returns the directory for accessing resources from Aleo storage.
 /// The expected directory path to be returned is `~/.aleo/`.
 ///
+/// The directory may be overridden and is resolved in the following order of precedence:
+/// 1. The innermost `scoped_aleo_root` of the calling thread.
+/// 2. The process-wide root given to `set_aleo_root`.
+/// 3. The `ALEO_HOME` environment variable, if it is set and non-empty.
+/// 4. The `aleo` directory in `$XDG_DATA_HOME`, if the XDG layout is in effect (see `LayoutPolicy`).
+/// 5. The `.aleo` directory in the user's home directory.
+///
 pub fn aleo_dir() -> PathBuf {
-    // Locate the home directory as the starting point.
-    // If called on a non-standard OS, use the repository directory.
-    let mut path = match home_dir() {
-        Some(home) => home,
-        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")),
-    };
-    // Append the Aleo directory to the path.
-    path.push(ALEO_DIRECTORY);
-    path
+    aleo_data_dir()
+}
+
+///
+/// Returns the directory for accessing resources from Aleo storage, as given by `aleo_dir`.
+///
+/// Unlike `aleo_dir`, this returns an error instead of falling back to the current directory
+/// if the home directory cannot be determined, or of ignoring an unrecognized `ALEO_DIR_LAYOUT`,
+/// and checks that the directory is valid UTF-8 and is either a writable directory or may be created.
+///
+pub fn try_aleo_dir() -> Result<PathBuf, StorageError> {
+    let path = directories::try_aleo_data_dir()?;
+    error::validate_dir(&path)?;
+    Ok(path)
 }
 
 ///
 /// Returns the directory for accessing the ledger files from Aleo storage.
 ///
+/// This is a compatibility shim for `aleo_network_ledger_dir`, taking the network ID rather than a `Network`.
+/// The directory is named after the network ID, e.g. `ledger-0`, as it always was; see `migrate_network_ledger_dir`
+/// to move it to the name of the network.
+///
+pub fn aleo_ledger_dir(network: u16, mode: &StorageMode) -> PathBuf {
+    resolve_ledger_dir(&network, mode).path
+}
+
+///
+/// Returns the directory for accessing the ledger files of the given network from Aleo storage.
+///
 /// In production mode, the expected directory path is `~/.aleo/storage/ledger-{network}`.
-/// In development mode, the expected directory path is `/path/to/repo/.ledger-{network}-{id}`.
+/// In development mode, the expected directory path is `/path/to/repo/.ledger-{network}-{id}`,
+/// where the repository is the development root (see `development_root`).
 /// In custom mode, the expected directory path is `/path/to/custom`.
 ///
-pub fn aleo_ledger_dir(network: u16, mode: &StorageMode) -> PathBuf {
+/// The network is named after a known network, e.g. `ledger-mainnet`, or its ID for a custom network,
+/// e.g. `ledger-7`. A ledger directory named after the ID of a known network, e.g. `ledger-0`, as given by
+/// `aleo_ledger_dir`, is moved to this directory by `migrate_network_ledger_dir`.
+///
+pub fn aleo_network_ledger_dir(network: Network, mode: &StorageMode) -> PathBuf {
+    resolve_network_ledger_dir(network, mode).path
+}
+
+///
+/// Returns the directory for accessing the ledger files of the given network from Aleo storage,
+/// as given by `aleo_network_ledger_dir`, along with the source it was resolved from.
+///
+pub fn resolve_network_ledger_dir(network: Network, mode: &StorageMode) -> ResolvedLedgerDir {
+    resolve_ledger_dir(&network, mode)
+}
+
+/// Returns the ledger directory of the network with the given name or ID, along with the source it was resolved from.
+fn resolve_ledger_dir(network: &dyn fmt::Display, mode: &StorageMode) -> ResolvedLedgerDir {
     // Construct the path to the ledger in storage.
-    match mode {
+    let (path, source) = match mode {
         // In production mode, the ledger is stored in the `~/.aleo/` directory.
-        StorageMode::Production => {
-            let mut path = aleo_dir();
-            path.push("storage");
-            path.push(format!("ledger-{}", network));
-            path
-        }
-        // In development mode, the ledger files are stored in a hidden folder in the repository root directory.
+        StorageMode::Production => (production_ledger_dir(aleo_dir(), network), LedgerDirSource::AleoDir),
+        // In development mode, the ledger files are stored in a hidden folder in the development root directory.
         StorageMode::Development(id) => {
-            let mut path = match std::env::current_dir() {
-                Ok(current_dir) => current_dir,
-                _ => PathBuf::from(env!("CARGO_MANIFEST_DIR")),
-            };
-            path.push(format!(".ledger-{}-{}", network, id));
-            path
+            let root = development_root();
+            (
+                development_ledger_dir(root.path, network, *id),
+                LedgerDirSource::DevelopmentRoot(root.source),
+            )
         }
         // In custom mode, the ledger files are stored in the given directory path.
-        StorageMode::Custom(path) => path.to_owned(),
+        StorageMode::Custom(path) => (path.to_owned(), LedgerDirSource::Custom),
         StorageMode::Test(tempdir) => {
             if let Some(tempdir) = tempdir {
-                tempdir.path().to_owned()
+                (tempdir.path().to_owned(), LedgerDirSource::Test)
             } else {
                 // aleo_ledger_dir is only ever called with persistent storage, where TempDir must be present.
                 panic!("StorageMode::Test was created in a persistent storage context without a TempDir");
             }
         }
-    }
+    };
+    ResolvedLedgerDir { path, source }
+}
+
+///
+/// Returns the directory for accessing the ledger files from Aleo storage, as given by `aleo_ledger_dir`.
+///
+/// This is a compatibility shim for `try_aleo_network_ledger_dir`, taking the network ID rather than a `Network`.
+///
+pub fn try_aleo_ledger_dir(network: u16, mode: &StorageMode) -> Result<PathBuf, StorageError> {
+    try_ledger_dir(&network, mode)
+}
+
+///
+/// Returns the directory for accessing the ledger files of the given network from Aleo storage,
+/// as given by `aleo_network_ledger_dir`.
+///
+/// Unlike `aleo_network_ledger_dir`, this returns an error instead of panicking on `StorageMode::Test(None)`
+/// or falling back to the current directory, and checks that the directory is valid UTF-8
+/// and is either a writable directory or may be created.
+///
+pub fn try_aleo_network_ledger_dir(network: Network, mode: &StorageMode) -> Result<PathBuf, StorageError> {
+    try_ledger_dir(&network, mode)
+}
+
+/// Returns the ledger directory of the network with the given name or ID, as given by `try_aleo_network_ledger_dir`.
+fn try_ledger_dir(network: &dyn fmt::Display, mode: &StorageMode) -> Result<PathBuf, StorageError> {
+    let path = match mode {
+        StorageMode::Production => production_ledger_dir(directories::try_aleo_data_dir()?, network),
+        StorageMode::Development(id) => development_ledger_dir(development::try_development_root()?.path, network, *id),
+        StorageMode::Custom(path) => path.to_owned(),
+        StorageMode::Test(Some(tempdir)) if tempdir.path().is_dir() => tempdir.path().to_owned(),
+        StorageMode::Test(_) => return Err(StorageError::InvalidTestDirectory),
+    };
+    error::validate_dir(&path)?;
+    Ok(path)
+}
+
+/// Returns the production ledger directory within the given Aleo directory.
+fn production_ledger_dir(aleo_dir: PathBuf, network: &dyn fmt::Display) -> PathBuf {
+    aleo_dir.join("storage").join(format!("ledger-{network}"))
+}
+
+/// Returns the development ledger directory within the given root directory.
+fn development_ledger_dir(root: PathBuf, network: &dyn fmt::Display, id: u16) -> PathBuf {
+    root.join(format!(".ledger-{network}-{id}"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    /// Serializes the tests that modify the process environment or other process-wide state.
+    pub(crate) fn env_lock() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
 
     #[test]
     fn test_aleo_dir() {
@@ -157,4 +469,140 @@ mod tests {
             aleo_ledger_dir(2, &StorageMode::Production).exists()
         );
     }
+
+    #[test]
+    fn test_aleo_network_ledger_dir() {
+        let _guard = scoped_aleo_root("/aleo");
+        let ledger = aleo_network_ledger_dir(Network::MAINNET, &StorageMode::Production);
+        assert_eq!(ledger, PathBuf::from("/aleo/storage/ledger-mainnet"));
+        // The compatibility shim keeps naming the directory after the network ID.
+        assert_eq!(
+            aleo_ledger_dir(0, &StorageMode::Production),
+            PathBuf::from("/aleo/storage/ledger-0")
+        );
+        assert_eq!(
+            aleo_ledger_dir(7, &StorageMode::Production),
+            PathBuf::from("/aleo/storage/ledger-7")
+        );
+
+        let ledger = aleo_network_ledger_dir(Network::CANARY, &StorageMode::Development(1));
+        assert!(ledger.ends_with(".ledger-canary-1"));
+    }
+
+    #[test]
+    fn test_resolve_network_ledger_dir() {
+        let _lock = env_lock();
+        let _guard = scoped_aleo_root("/aleo");
+        assert_eq!(
+            resolve_network_ledger_dir(Network::MAINNET, &StorageMode::Production).source,
+            LedgerDirSource::AleoDir
+        );
+
+        set_development_root("/repo");
+        assert_eq!(
+            resolve_network_ledger_dir(Network::TESTNET, &StorageMode::Development(2)),
+            ResolvedLedgerDir {
+                path: PathBuf::from("/repo/.ledger-testnet-2"),
+                source: LedgerDirSource::DevelopmentRoot(DevRootSource::Explicit),
+            }
+        );
+        clear_development_root();
+    }
+
+    #[test]
+    fn test_try_aleo_dir() {
+        let tempdir = TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(tempdir.path().join("aleo"));
+        assert_eq!(try_aleo_dir().unwrap(), tempdir.path().join("aleo"));
+
+        // A file in place of the directory, or one of its ancestors, is reported.
+        std::fs::write(tempdir.path().join("file"), b"").unwrap();
+        let _guard = scoped_aleo_root(tempdir.path().join("file").join("aleo"));
+        assert!(
+            matches!(try_aleo_dir(), Err(StorageError::NotADirectory(path)) if path == tempdir.path().join("file"))
+        );
+    }
+
+    #[test]
+    fn test_try_aleo_ledger_dir() {
+        let tempdir = TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(tempdir.path());
+        assert_eq!(
+            try_aleo_ledger_dir(2, &StorageMode::Production).unwrap(),
+            aleo_ledger_dir(2, &StorageMode::Production)
+        );
+
+        let mode = StorageMode::new_test(None);
+        assert_eq!(try_aleo_ledger_dir(2, &mode).unwrap(), aleo_ledger_dir(2, &mode));
+
+        // The test mode without a temporary directory is an error rather than a panic.
+        assert!(matches!(
+            try_aleo_ledger_dir(2, &StorageMode::Test(None)),
+            Err(StorageError::InvalidTestDirectory)
+        ));
+    }
+
+    #[test]
+    fn test_storage_mode_from_str() {
+        for mode in [
+            StorageMode::Production,
+            StorageMode::Development(3),
+            StorageMode::Custom(PathBuf::from("/data/ledger:1")),
+        ] {
+            assert_eq!(mode.to_string().parse::<StorageMode>().unwrap(), mode);
+        }
+        assert_eq!(
+            "development:3".parse::<StorageMode>().unwrap(),
+            StorageMode::Development(3)
+        );
+        assert_eq!(
+            StorageMode::Custom(PathBuf::from("/data/ledger")).to_string(),
+            "custom:/data/ledger"
+        );
+
+        // A test mode is parsed into a new temporary directory.
+        let mode = "test".parse::<StorageMode>().unwrap();
+        assert!(aleo_ledger_dir(1, &mode).is_dir());
+        assert_eq!(mode.to_string(), "test");
+        assert_ne!(mode.to_string().parse::<StorageMode>().unwrap(), mode);
+
+        for invalid in ["", "Production", "dev", "dev:", "dev:70000", "custom:", "test:/tmp"] {
+            assert!(invalid.parse::<StorageMode>().is_err(), "'{invalid}' should not parse");
+        }
+    }
+
+    #[test]
+    fn test_storage_mode_custom() {
+        let mode = StorageMode::custom("/data/ledger").unwrap();
+        assert_eq!(mode, StorageMode::Custom(PathBuf::from("/data/ledger")));
+        assert_eq!(mode.to_string().parse::<StorageMode>().unwrap(), mode);
+
+        // An empty path would be formatted as `custom:`, which does not parse.
+        assert!(matches!(
+            StorageMode::custom(""),
+            Err(ParseStorageModeError::InvalidPath(path)) if path.as_os_str().is_empty()
+        ));
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+            let path = PathBuf::from(OsStr::from_bytes(b"/data/ledger-\xff"));
+            assert!(matches!(
+                StorageMode::custom(path),
+                Err(ParseStorageModeError::InvalidPath(_))
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_try_aleo_ledger_dir_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let mode = StorageMode::Custom(PathBuf::from(OsStr::from_bytes(b"/tmp/ledger-\xff")));
+        assert!(matches!(
+            try_aleo_ledger_dir(2, &mode),
+            Err(StorageError::NonUtf8Path(_))
+        ));
+    }
 }
@@ -0,0 +1,369 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Network, StorageMode, aleo_network_ledger_dir, snapshot::recover_restore};
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the lock file within a ledger directory.
+pub const LEDGER_LOCK_FILE: &str = ".aleo-ledger.lock";
+
+/// The process holding a ledger directory lock.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockHolder {
+    /// The process ID.
+    pub pid: u32,
+    /// The name of the host the process runs on.
+    pub hostname: String,
+    /// The time the lock was acquired, in seconds since the Unix epoch.
+    pub started_at: u64,
+}
+
+impl LockHolder {
+    /// Returns the lock holder describing the current process.
+    fn current() -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            pid: std::process::id(),
+            hostname: hostname(),
+            started_at,
+        }
+    }
+
+    /// Parses the lock holder from the contents of a lock file.
+    fn parse(contents: &str) -> Option<Self> {
+        let (mut pid, mut hostname, mut started_at) = (None, None, None);
+        for line in contents.lines() {
+            match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("pid", value)) => pid = value.parse().ok(),
+                Some(("hostname", value)) => hostname = Some(value.to_string()),
+                Some(("started_at", value)) => started_at = value.parse().ok(),
+                _ => continue,
+            }
+        }
+        Some(Self {
+            pid: pid?,
+            hostname: hostname?,
+            started_at: started_at?,
+        })
+    }
+
+    /// Returns `true` if the holder is a process on this host that is still running.
+    /// A process on another host is assumed to be running, as there is no way to tell.
+    fn is_alive(&self) -> bool {
+        self.hostname != hostname() || is_process_alive(self.pid)
+    }
+}
+
+impl fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "process {} on host '{}', started at {} (Unix time)",
+            self.pid, self.hostname, self.started_at
+        )
+    }
+}
+
+/// An error that occurs when locking a ledger directory.
+#[derive(Debug)]
+pub enum LockError {
+    /// The ledger directory is locked by another process.
+    AlreadyLocked {
+        /// The path of the lock file.
+        path: PathBuf,
+        /// The process holding the lock, if it could be read from the lock file.
+        holder: Option<LockHolder>,
+    },
+    /// An I/O error occurred while locking the ledger directory.
+    Io(io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyLocked {
+                path,
+                holder: Some(holder),
+            } => {
+                write!(
+                    f,
+                    "the ledger directory is already in use by {holder} (lock file {})",
+                    path.display()
+                )
+            }
+            Self::AlreadyLocked { path, holder: None } => {
+                write!(
+                    f,
+                    "the ledger directory is already in use by another process (lock file {})",
+                    path.display()
+                )
+            }
+            Self::Io(error) => write!(f, "failed to lock the ledger directory: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for LockError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::AlreadyLocked { .. } => None,
+            Self::Io(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for LockError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+///
+/// An exclusive, advisory lock on a ledger directory, which is released when dropped.
+///
+/// The lock is held on the `.aleo-ledger.lock` file within the ledger directory, which records the holder's
+/// process ID, hostname and start time. As the operating system releases the lock when its holder exits,
+/// a lock file left behind by a process that died is reported as stale and taken over.
+/// On filesystems without support for file locking, the recorded process ID is checked instead.
+///
+#[derive(Debug)]
+pub struct LedgerDirLock {
+    /// The open lock file.
+    file: File,
+    /// The path of the lock file.
+    path: PathBuf,
+    /// The current process, as recorded in the lock file.
+    holder: LockHolder,
+    /// The previous holder, if it exited without releasing the lock.
+    stale_holder: Option<LockHolder>,
+}
+
+impl LedgerDirLock {
+    ///
+    /// Locks the ledger directory of the given network and storage mode, creating it if it does not exist.
+    ///
+    /// Returns `LockError::AlreadyLocked` if the directory is in use by another process.
+    ///
+    pub fn acquire(network: Network, mode: &StorageMode) -> Result<Self, LockError> {
+        Self::acquire_dir(aleo_network_ledger_dir(network, mode))
+    }
+
+    ///
+    /// Locks the given ledger directory, creating it if it does not exist.
+    ///
+    /// A restore of a snapshot or an archive that was interrupted is rolled back or completed once the lock is taken,
+    /// so that the ledger directory holds either its previous or its new contents (see `SnapshotStore::restore`).
+    ///
+    /// Returns `LockError::AlreadyLocked` if the directory is in use by another process.
+    ///
+    pub fn acquire_dir<P: AsRef<Path>>(dir: P) -> Result<Self, LockError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let path = dir.join(LEDGER_LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        // Take the lock, falling back to the recorded process ID if the filesystem does not support locking.
+        let previous_holder = match file.try_lock() {
+            Ok(()) => read_holder(&mut file),
+            Err(TryLockError::WouldBlock) => {
+                return Err(LockError::AlreadyLocked {
+                    holder: read_holder(&mut file),
+                    path,
+                });
+            }
+            Err(TryLockError::Error(error)) if error.kind() == io::ErrorKind::Unsupported => {
+                let previous_holder = read_holder(&mut file);
+                if let Some(holder) = previous_holder.as_ref().filter(|holder| holder.is_alive()) {
+                    return Err(LockError::AlreadyLocked {
+                        holder: Some(holder.clone()),
+                        path,
+                    });
+                }
+                previous_holder
+            }
+            Err(TryLockError::Error(error)) => return Err(error.into()),
+        };
+
+        // Record the current process as the holder.
+        let holder = LockHolder::current();
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(
+            file,
+            "pid = {}\nhostname = {}\nstarted_at = {}\n",
+            holder.pid, holder.hostname, holder.started_at
+        )?;
+        file.sync_all()?;
+
+        let lock = Self {
+            file,
+            path,
+            holder,
+            stale_holder: previous_holder,
+        };
+        recover_restore(dir)?;
+        Ok(lock)
+    }
+
+    /// Returns the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the current process, as recorded in the lock file.
+    pub const fn holder(&self) -> &LockHolder {
+        &self.holder
+    }
+
+    /// Returns the previous holder of the lock, if it exited without releasing it.
+    pub const fn stale_holder(&self) -> Option<&LockHolder> {
+        self.stale_holder.as_ref()
+    }
+}
+
+impl Drop for LedgerDirLock {
+    fn drop(&mut self) {
+        // The lock file is emptied rather than removed, as another process may already have it open.
+        // An empty lock file indicates that the lock was released cleanly.
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Reads the lock holder from the given lock file, if it is recorded.
+fn read_holder(file: &mut File) -> Option<LockHolder> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    LockHolder::parse(&contents)
+}
+
+/// Returns the hostname of the current machine.
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // Safety: the buffer is valid for writes of its length, and `gethostname` does not write past it.
+        if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } == 0 {
+            let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+            return String::from_utf8_lossy(&buffer[..length]).into_owned();
+        }
+    }
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Returns `true` if a process with the given ID is running on this host.
+pub(crate) fn is_process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        // Safety: sending the null signal only checks whether the process exists.
+        let result = unsafe { libc::kill(pid, 0) };
+        result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        // Without a portable check, the process is assumed to be running.
+        let _ = pid;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_dir_lock() {
+        let mode = StorageMode::new_test(None);
+
+        let lock = LedgerDirLock::acquire(Network::TESTNET, &mode).unwrap();
+        assert_eq!(lock.holder().pid, std::process::id());
+        assert!(lock.stale_holder().is_none());
+
+        // A second lock on the same directory names the holder.
+        match LedgerDirLock::acquire(Network::TESTNET, &mode) {
+            Err(LockError::AlreadyLocked {
+                holder: Some(holder), ..
+            }) => assert_eq!(&holder, lock.holder()),
+            other => panic!("expected the directory to be locked, found {other:?}"),
+        }
+
+        // Once released, the lock may be acquired again, without a stale holder.
+        drop(lock);
+        let lock = LedgerDirLock::acquire(Network::TESTNET, &mode).unwrap();
+        assert!(lock.stale_holder().is_none());
+    }
+
+    #[test]
+    fn test_stale_ledger_dir_lock() {
+        let mode = StorageMode::new_test(None);
+        let dir = aleo_network_ledger_dir(Network::TESTNET, &mode);
+
+        // Leave a lock file behind, as a process that crashed would.
+        let stale = LockHolder {
+            pid: u32::MAX,
+            hostname: hostname(),
+            started_at: 1,
+        };
+        fs::write(
+            dir.join(LEDGER_LOCK_FILE),
+            format!(
+                "pid = {}\nhostname = {}\nstarted_at = {}\n",
+                stale.pid, stale.hostname, stale.started_at
+            ),
+        )
+        .unwrap();
+
+        let lock = LedgerDirLock::acquire(Network::TESTNET, &mode).unwrap();
+        assert_eq!(lock.stale_holder(), Some(&stale));
+        assert!(!stale.is_alive());
+    }
+
+    #[test]
+    fn test_lock_error_names_holder() {
+        let holder = LockHolder {
+            pid: 42,
+            hostname: "validator-1".to_string(),
+            started_at: 1700000000,
+        };
+        let error = LockError::AlreadyLocked {
+            path: PathBuf::from("/ledger/.aleo-ledger.lock"),
+            holder: Some(holder),
+        };
+        assert!(error.to_string().contains("process 42 on host 'validator-1'"));
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::StorageLayout;
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The extension of a log file.
+const LOG_EXTENSION: &str = "log";
+
+/// The extension of a compressed log file.
+const COMPRESSED_EXTENSION: &str = "gz";
+
+/// When a log file is rotated, and how many rotated log files are retained.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LogRotation {
+    /// The size at which the active log file is rotated, if any.
+    pub max_file_bytes: Option<u64>,
+    /// The age at which the active log file is rotated, if any.
+    pub max_age: Option<Duration>,
+    /// The maximum total size of the log files. The oldest rotated log files are removed beyond it whenever
+    /// the active log file is opened or rotated, so the active log file may take it over until it is rotated.
+    pub max_total_bytes: u64,
+    /// Whether to compress the rotated log files with gzip, which requires the `compression` feature.
+    /// Without it, the rotated log files are left uncompressed.
+    pub compress: bool,
+}
+
+impl Default for LogRotation {
+    /// Rotates the log file daily or at 100 MiB, compresses it if the `compression` feature is enabled,
+    /// and retains up to 1 GiB of log files.
+    fn default() -> Self {
+        Self {
+            max_file_bytes: Some(100 << 20),
+            max_age: Some(Duration::from_secs(24 * 60 * 60)),
+            max_total_bytes: 1 << 30,
+            compress: cfg!(feature = "compression"),
+        }
+    }
+}
+
+///
+/// A log file that is rotated by size and age, e.g. `node.log`, which is renamed to `node.{timestamp}.log`
+/// (and compressed to `node.{timestamp}.log.gz`) when rotated.
+///
+/// The log file is only rotated at the start of a line, so that a line is never split across two log files.
+/// Rotated log files are compressed on the writing thread.
+///
+#[derive(Debug)]
+pub struct RotatingLog {
+    /// The directory of the log files.
+    dir: PathBuf,
+    /// The base name of the log files, e.g. `node`.
+    name: String,
+    /// The rotation policy.
+    rotation: LogRotation,
+    /// The active log file.
+    file: File,
+    /// The size of the active log file.
+    size: u64,
+    /// The time the active log file was created.
+    created_at: SystemTime,
+    /// Whether the last byte written ends a line.
+    at_line_start: bool,
+}
+
+impl RotatingLog {
+    ///
+    /// Opens the log file with the given base name in the logs directory of the given storage layout,
+    /// e.g. `~/.aleo/logs-mainnet/node.log` in production mode.
+    ///
+    pub fn open_for_layout(layout: &StorageLayout, name: &str, rotation: LogRotation) -> io::Result<Self> {
+        Self::open(layout.logs_dir(), name, rotation)
+    }
+
+    ///
+    /// Opens the log file with the given base name in the given directory, appending to it if it exists,
+    /// and creating the directory if it is missing.
+    ///
+    /// The log files beyond the maximum total size are removed.
+    ///
+    pub fn open<P: Into<PathBuf>>(dir: P, name: &str, rotation: LogRotation) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{name}.{LOG_EXTENSION}"));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let created_at = metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        let log = Self {
+            dir,
+            name: name.to_string(),
+            rotation,
+            file,
+            size: metadata.len(),
+            created_at,
+            at_line_start: true,
+        };
+        log.enforce_retention()?;
+        Ok(log)
+    }
+
+    /// Returns the path of the active log file.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.{LOG_EXTENSION}", self.name))
+    }
+
+    /// Returns the rotated log files, from the oldest to the newest.
+    pub fn rotated_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if let Some(timestamp) = self.rotated_timestamp(&entry.file_name().to_string_lossy()) {
+                files.push((timestamp, entry.path()));
+            }
+        }
+        files.sort();
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// Rotates the active log file, unless it is empty.
+    pub fn rotate(&mut self) -> io::Result<()> {
+        if self.size == 0 {
+            return Ok(());
+        }
+        self.file.sync_all()?;
+        let rotated = self.rotated_path()?;
+        fs::rename(self.path(), &rotated)?;
+        self.file = OpenOptions::new().create(true).append(true).open(self.path())?;
+        self.size = 0;
+        self.created_at = SystemTime::now();
+        if self.rotation.compress {
+            compress(&rotated)?;
+        }
+        self.enforce_retention()
+    }
+
+    /// Returns `true` if the active log file should be rotated before writing the given number of bytes.
+    fn should_rotate(&self, len: usize) -> bool {
+        let is_full = self
+            .rotation
+            .max_file_bytes
+            .is_some_and(|max| self.size + len as u64 > max);
+        let is_old = self.rotation.max_age.is_some_and(|max_age| {
+            SystemTime::now()
+                .duration_since(self.created_at)
+                .is_ok_and(|age| age >= max_age)
+        });
+        self.size > 0 && self.at_line_start && (is_full || is_old)
+    }
+
+    /// Returns a path for the log file being rotated now, e.g. `node.1700000000.log`, which sorts after the others.
+    fn rotated_path(&self) -> io::Result<PathBuf> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let newest = self
+            .rotated_files()?
+            .last()
+            .and_then(|path| self.rotated_timestamp(&path.file_name()?.to_string_lossy()));
+        // The log file may be rotated several times within a second, or after the clock went back.
+        let name = match newest {
+            Some((timestamp, i)) if timestamp >= now => format!("{}.{timestamp}-{}.{LOG_EXTENSION}", self.name, i + 1),
+            _ => format!("{}.{now}.{LOG_EXTENSION}", self.name),
+        };
+        Ok(self.dir.join(name))
+    }
+
+    /// Returns the time and sequence number of the given rotated log file, if it is one of this log.
+    fn rotated_timestamp(&self, file_name: &str) -> Option<(u64, u64)> {
+        let rest = file_name.strip_prefix(&self.name)?.strip_prefix('.')?;
+        let rest = rest
+            .strip_suffix(COMPRESSED_EXTENSION)
+            .and_then(|rest| rest.strip_suffix('.'))
+            .unwrap_or(rest);
+        let stamp = rest.strip_suffix(LOG_EXTENSION)?.strip_suffix('.')?;
+        match stamp.split_once('-') {
+            Some((timestamp, i)) => Some((timestamp.parse().ok()?, i.parse().ok()?)),
+            None => Some((stamp.parse().ok()?, 0)),
+        }
+    }
+
+    /// Removes the oldest rotated log files until the log files fit in the maximum total size.
+    fn enforce_retention(&self) -> io::Result<()> {
+        let rotated = self.rotated_files()?;
+        let mut sizes = Vec::with_capacity(rotated.len());
+        for path in &rotated {
+            sizes.push(fs::metadata(path)?.len());
+        }
+        let mut total = self.size + sizes.iter().sum::<u64>();
+        for (path, size) in rotated.iter().zip(sizes) {
+            if total <= self.rotation.max_total_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Compresses the given log file with gzip, replacing it.
+#[cfg(feature = "compression")]
+fn compress(path: &Path) -> io::Result<()> {
+    use flate2::{Compression, write::GzEncoder};
+
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(format!(".{COMPRESSED_EXTENSION}"));
+    let compressed = PathBuf::from(compressed);
+    let result = (|| {
+        let mut encoder = GzEncoder::new(File::create_new(&compressed)?, Compression::default());
+        io::copy(&mut File::open(path)?, &mut encoder)?;
+        encoder.finish()?.sync_all()
+    })();
+    match result {
+        Ok(()) => fs::remove_file(path),
+        Err(error) => {
+            let _ = fs::remove_file(&compressed);
+            Err(error)
+        }
+    }
+}
+
+/// Leaves the given log file uncompressed, as the `compression` feature is disabled.
+#[cfg(not(feature = "compression"))]
+fn compress(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_by_size() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let rotation = LogRotation {
+            max_file_bytes: Some(16),
+            max_age: None,
+            max_total_bytes: u64::MAX,
+            compress: false,
+        };
+        let mut log = RotatingLog::open(tempdir.path().join("logs"), "node", rotation).unwrap();
+        for i in 0..5 {
+            writeln!(log, "line {i} ......").unwrap();
+        }
+        assert_eq!(fs::read_to_string(log.path()).unwrap(), "line 4 ......\n");
+        let rotated = log.rotated_files().unwrap();
+        assert_eq!(rotated.len(), 4);
+        assert_eq!(fs::read_to_string(&rotated[0]).unwrap(), "line 0 ......\n");
+        assert_eq!(fs::read_to_string(&rotated[3]).unwrap(), "line 3 ......\n");
+
+        // An empty log file is not rotated.
+        let mut log = RotatingLog::open(tempdir.path().join("logs"), "empty", rotation).unwrap();
+        log.rotate().unwrap();
+        assert!(log.rotated_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rotate_by_age_and_retention() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let rotation = LogRotation {
+            max_file_bytes: None,
+            max_age: Some(Duration::ZERO),
+            max_total_bytes: 20,
+            compress: false,
+        };
+        let mut log = RotatingLog::open(tempdir.path(), "node", rotation).unwrap();
+        for i in 0..5 {
+            writeln!(log, "line {i} ..").unwrap();
+        }
+        // Every log file is 10 bytes, so only the 2 newest rotated ones are retained.
+        let rotated = log.rotated_files().unwrap();
+        assert_eq!(rotated.len(), 2);
+        assert_eq!(fs::read_to_string(&rotated[0]).unwrap(), "line 2 ..\n");
+        assert_eq!(fs::read_to_string(log.path()).unwrap(), "line 4 ..\n");
+
+        // The retention is enforced on open, e.g. after lowering the maximum total size.
+        drop(log);
+        let log = RotatingLog::open(tempdir.path(), "node", LogRotation {
+            max_total_bytes: 10,
+            ..rotation
+        })
+        .unwrap();
+        assert!(log.rotated_files().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_rotate_compressed() {
+        use std::io::Read;
+
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let mut log = RotatingLog::open(tempdir.path(), "node", LogRotation::default()).unwrap();
+        writeln!(log, "started").unwrap();
+        log.rotate().unwrap();
+        let rotated = log.rotated_files().unwrap();
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(rotated[0].extension().unwrap(), "gz");
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(File::open(&rotated[0]).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "started\n");
+    }
+}
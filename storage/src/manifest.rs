@@ -0,0 +1,172 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Network, write_atomic};
+
+use std::{
+    fs,
+    io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the version manifest within a ledger directory.
+pub const LEDGER_MANIFEST_FILE: &str = ".aleo-manifest";
+
+///
+/// The version manifest of a ledger directory, which records the on-disk format of its contents.
+///
+/// The manifest is stored as `key = value` lines in the `.aleo-manifest` file within the ledger directory.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerManifest {
+    /// The version of the on-disk format.
+    pub format_version: u32,
+    /// The network of the ledger.
+    pub network: Network,
+    /// The version of the software that created the ledger directory.
+    pub software_version: String,
+    /// The time the ledger directory was created, in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// The time the manifest was last updated, in seconds since the Unix epoch.
+    pub updated_at: u64,
+    /// The name of the migration that was started but has not completed, if any.
+    pub pending_migration: Option<String>,
+}
+
+impl LedgerManifest {
+    /// Initializes a new manifest for a ledger directory created now.
+    pub fn new<S: Into<String>>(format_version: u32, network: Network, software_version: S) -> Self {
+        let now = unix_now();
+        Self {
+            format_version,
+            network,
+            software_version: software_version.into(),
+            created_at: now,
+            updated_at: now,
+            pending_migration: None,
+        }
+    }
+
+    ///
+    /// Loads the manifest of the given ledger directory.
+    ///
+    /// Returns `None` if the directory has no manifest, and an error of kind `io::ErrorKind::InvalidData`
+    /// if the manifest is malformed.
+    ///
+    pub fn load<P: AsRef<Path>>(dir: P) -> io::Result<Option<Self>> {
+        let path = dir.as_ref().join(LEDGER_MANIFEST_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        Self::parse(&contents).map(Some).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed ledger manifest {}", path.display()),
+            )
+        })
+    }
+
+    ///
+    /// Stores the manifest in the given ledger directory, creating the directory if it does not exist.
+    ///
+    /// The manifest is written atomically (see `write_atomic`), so that an interrupted write
+    /// leaves either the previous or the new manifest in place.
+    ///
+    pub fn store<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        write_atomic(dir.as_ref().join(LEDGER_MANIFEST_FILE), self.to_string())
+    }
+
+    /// Parses the manifest from the contents of a manifest file.
+    fn parse(contents: &str) -> Option<Self> {
+        let (mut format_version, mut network, mut software_version) = (None, None, None);
+        let (mut created_at, mut updated_at, mut pending_migration) = (None, None, None);
+        for line in contents.lines() {
+            match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("format_version", value)) => format_version = value.parse().ok(),
+                Some(("network", value)) => network = value.parse().ok().map(Network::from_id),
+                Some(("software_version", value)) => software_version = Some(value.to_string()),
+                Some(("created_at", value)) => created_at = value.parse().ok(),
+                Some(("updated_at", value)) => updated_at = value.parse().ok(),
+                Some(("pending_migration", value)) => pending_migration = Some(value.to_string()),
+                _ => continue,
+            }
+        }
+        Some(Self {
+            format_version: format_version?,
+            network: network?,
+            software_version: software_version?,
+            created_at: created_at?,
+            updated_at: updated_at?,
+            pending_migration,
+        })
+    }
+
+    /// Marks the manifest as updated now.
+    pub(crate) fn touch(&mut self) {
+        self.updated_at = unix_now();
+    }
+}
+
+impl std::fmt::Display for LedgerManifest {
+    /// Formats the manifest as the contents of a manifest file.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "format_version = {}", self.format_version)?;
+        writeln!(f, "network = {}", self.network.id())?;
+        writeln!(f, "software_version = {}", self.software_version)?;
+        writeln!(f, "created_at = {}", self.created_at)?;
+        writeln!(f, "updated_at = {}", self.updated_at)?;
+        if let Some(migration) = &self.pending_migration {
+            writeln!(f, "pending_migration = {migration}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the current time, in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_manifest() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        assert_eq!(LedgerManifest::load(tempdir.path()).unwrap(), None);
+
+        let mut manifest = LedgerManifest::new(3, Network::TESTNET, "snarkos 3.1.0");
+        manifest.store(tempdir.path()).unwrap();
+        assert_eq!(LedgerManifest::load(tempdir.path()).unwrap(), Some(manifest.clone()));
+
+        manifest.pending_migration = Some("split-blocks".to_string());
+        manifest.store(tempdir.path()).unwrap();
+        assert_eq!(LedgerManifest::load(tempdir.path()).unwrap(), Some(manifest));
+
+        fs::write(tempdir.path().join(LEDGER_MANIFEST_FILE), "format_version = three\n").unwrap();
+        assert_eq!(
+            LedgerManifest::load(tempdir.path()).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
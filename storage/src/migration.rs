@@ -0,0 +1,384 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    LedgerDirLock,
+    LedgerManifest,
+    LockError,
+    Network,
+    StorageMode,
+    aleo_network_ledger_dir,
+    snapshot::is_ledger_entry,
+};
+
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+/// The error type returned by a migration step.
+pub type MigrationStepError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A migration step, which upgrades the contents of a ledger directory by one format version.
+type MigrationStep = Box<dyn Fn(&Path) -> Result<(), MigrationStepError> + Send + Sync>;
+
+/// An error that occurs when opening or migrating a ledger directory.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The ledger directory could not be locked.
+    Lock(LockError),
+    /// An I/O error occurred while reading or writing the manifest.
+    Io(io::Error),
+    /// The ledger directory belongs to another network.
+    NetworkMismatch {
+        /// The network the ledger directory was opened for.
+        expected: Network,
+        /// The network recorded in the manifest.
+        found: Network,
+    },
+    /// The ledger directory was written in a newer format than this software supports.
+    UnsupportedVersion {
+        /// The format version recorded in the manifest.
+        found: u32,
+        /// The newest format version this software supports.
+        supported: u32,
+    },
+    /// No migration is registered from the given format version.
+    MissingMigration {
+        /// The format version without a migration.
+        from_version: u32,
+    },
+    /// A migration step failed, leaving the ledger directory at its starting format version.
+    Step {
+        /// The name of the migration step.
+        name: String,
+        /// The format version the step migrates from.
+        from_version: u32,
+        /// The error returned by the migration step.
+        error: MigrationStepError,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lock(error) => write!(f, "{error}"),
+            Self::Io(error) => write!(f, "failed to access the ledger manifest: {error}"),
+            Self::NetworkMismatch { expected, found } => {
+                write!(f, "the ledger directory belongs to network '{found}', not '{expected}'")
+            }
+            Self::UnsupportedVersion { found, supported } => write!(
+                f,
+                "the ledger directory has format version {found}, but at most version {supported} is supported"
+            ),
+            Self::MissingMigration { from_version } => {
+                write!(f, "no migration is registered from format version {from_version}")
+            }
+            Self::Step {
+                name,
+                from_version,
+                error,
+            } => {
+                write!(
+                    f,
+                    "migration '{name}' from format version {from_version} failed: {error}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Lock(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::Step { error, .. } => Some(error.as_ref()),
+            Self::NetworkMismatch { .. } | Self::UnsupportedVersion { .. } | Self::MissingMigration { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for MigrationError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<LockError> for MigrationError {
+    fn from(error: LockError) -> Self {
+        Self::Lock(error)
+    }
+}
+
+///
+/// A registry of the migration steps between the on-disk formats of a ledger directory.
+///
+/// Each step upgrades the directory from one format version to the next, and the manifest is updated after
+/// every step, so an interrupted migration resumes from the step that did not complete. Steps must therefore
+/// tolerate being re-run on a directory they have partially migrated.
+///
+/// A non-empty ledger directory without a manifest is treated as format version 0.
+///
+pub struct MigrationRegistry {
+    /// The format version written by this software.
+    current_version: u32,
+    /// The version of this software, recorded in new manifests.
+    software_version: String,
+    /// The migration steps, indexed by the format version they migrate from.
+    steps: BTreeMap<u32, (String, MigrationStep)>,
+}
+
+impl MigrationRegistry {
+    /// Initializes a new registry for software writing the given format version.
+    pub fn new<S: Into<String>>(current_version: u32, software_version: S) -> Self {
+        Self {
+            current_version,
+            software_version: software_version.into(),
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the format version written by this software.
+    pub const fn current_version(&self) -> u32 {
+        self.current_version
+    }
+
+    ///
+    /// Registers the migration step from `from_version` to `from_version + 1`.
+    ///
+    /// Panics if a step from `from_version` is already registered, or if it would migrate past the current version.
+    ///
+    pub fn register<S, F>(&mut self, from_version: u32, name: S, step: F) -> &mut Self
+    where
+        S: Into<String>,
+        F: Fn(&Path) -> Result<(), MigrationStepError> + Send + Sync + 'static,
+    {
+        assert!(
+            from_version < self.current_version,
+            "the migration from version {from_version} is not needed"
+        );
+        let previous = self.steps.insert(from_version, (name.into(), Box::new(step)));
+        assert!(
+            previous.is_none(),
+            "a migration from version {from_version} is already registered"
+        );
+        self
+    }
+
+    ///
+    /// Opens the ledger directory of the given network and storage mode,
+    /// holding its lock while any pending migration steps are run.
+    ///
+    pub fn open(&self, network: Network, mode: &StorageMode) -> Result<LedgerManifest, MigrationError> {
+        let dir = aleo_network_ledger_dir(network, mode);
+        let _lock = LedgerDirLock::acquire_dir(&dir)?;
+        self.open_dir(&dir, network)
+    }
+
+    ///
+    /// Opens the given ledger directory, running any pending migration steps, and returns its manifest.
+    ///
+    /// A new or empty directory is given a manifest of the current format version. The caller must ensure
+    /// no other process uses the directory, e.g. by holding a `LedgerDirLock`.
+    ///
+    pub fn open_dir(&self, dir: &Path, network: Network) -> Result<LedgerManifest, MigrationError> {
+        let mut manifest = match LedgerManifest::load(dir)? {
+            Some(manifest) => manifest,
+            None => {
+                let version = if is_empty_ledger_dir(dir)? {
+                    self.current_version
+                } else {
+                    0
+                };
+                let manifest = LedgerManifest::new(version, network, self.software_version.clone());
+                manifest.store(dir)?;
+                manifest
+            }
+        };
+
+        if manifest.network != network {
+            return Err(MigrationError::NetworkMismatch {
+                expected: network,
+                found: manifest.network,
+            });
+        }
+        if manifest.format_version > self.current_version {
+            return Err(MigrationError::UnsupportedVersion {
+                found: manifest.format_version,
+                supported: self.current_version,
+            });
+        }
+
+        while manifest.format_version < self.current_version {
+            let from_version = manifest.format_version;
+            let (name, step) = self
+                .steps
+                .get(&from_version)
+                .ok_or(MigrationError::MissingMigration { from_version })?;
+
+            // Record the step before running it, so an interruption is visible in the manifest.
+            manifest.pending_migration = Some(name.clone());
+            manifest.touch();
+            manifest.store(dir)?;
+
+            step(dir).map_err(|error| MigrationError::Step {
+                name: name.clone(),
+                from_version,
+                error,
+            })?;
+
+            manifest.format_version = from_version + 1;
+            manifest.pending_migration = None;
+            manifest.touch();
+            manifest.store(dir)?;
+        }
+        Ok(manifest)
+    }
+}
+
+impl fmt::Debug for MigrationRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MigrationRegistry")
+            .field("current_version", &self.current_version)
+            .field("software_version", &self.software_version)
+            .field(
+                "steps",
+                &self
+                    .steps
+                    .iter()
+                    .map(|(version, (name, _))| (version, name))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+///
+/// Returns `true` if the given ledger directory does not exist, or holds no ledger entries, ignoring
+/// the lock file, restore staging and temporary files, as snapshots and archives do (see `is_ledger_path`).
+///
+fn is_empty_ledger_dir(dir: &Path) -> io::Result<bool> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => entries.try_fold(true, |is_empty, entry| Ok(is_empty && !is_ledger_entry(&entry?, &[]))),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(true),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    /// Returns a migration step that appends the given line to the `steps` file of the ledger directory.
+    fn append(line: &'static str) -> impl Fn(&Path) -> Result<(), MigrationStepError> {
+        move |dir| {
+            let mut steps = fs::read_to_string(dir.join("steps")).unwrap_or_default();
+            steps.push_str(line);
+            Ok(fs::write(dir.join("steps"), steps)?)
+        }
+    }
+
+    #[test]
+    fn test_new_ledger_dir() {
+        let mode = StorageMode::new_test(None);
+        let manifest = MigrationRegistry::new(2, "test 1.0.0")
+            .open(Network::TESTNET, &mode)
+            .unwrap();
+        assert_eq!(manifest.format_version, 2);
+        assert_eq!(manifest.software_version, "test 1.0.0");
+
+        // The directory belongs to its network.
+        let error = MigrationRegistry::new(2, "test 1.0.0")
+            .open(Network::MAINNET, &mode)
+            .unwrap_err();
+        assert!(matches!(error, MigrationError::NetworkMismatch {
+            found: Network::TESTNET,
+            ..
+        }));
+
+        // The directory may not be opened by older software.
+        let error = MigrationRegistry::new(1, "test 0.9.0")
+            .open(Network::TESTNET, &mode)
+            .unwrap_err();
+        assert!(matches!(error, MigrationError::UnsupportedVersion {
+            found: 2,
+            supported: 1
+        }));
+    }
+
+    #[test]
+    fn test_empty_ledger_dir() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let dir = tempdir.path().join("ledger");
+
+        // The lock file, restore staging and temporary files are not ledger entries.
+        fs::create_dir_all(dir.join(crate::snapshot::RESTORE_STAGING_DIR)).unwrap();
+        fs::write(dir.join(crate::LEDGER_LOCK_FILE), "").unwrap();
+        fs::write(dir.join(format!("CURRENT{}1", crate::atomic::TEMP_FILE_MARKER)), "").unwrap();
+        assert!(is_empty_ledger_dir(&dir).unwrap());
+        let manifest = MigrationRegistry::new(2, "test 1.0.0")
+            .open_dir(&dir, Network::TESTNET)
+            .unwrap();
+        assert_eq!(manifest.format_version, 2);
+
+        // Any other entry is an existing ledger, which predates the manifest.
+        fs::remove_file(dir.join(crate::LEDGER_MANIFEST_FILE)).unwrap();
+        fs::write(dir.join("CURRENT"), "MANIFEST-000001").unwrap();
+        assert!(!is_empty_ledger_dir(&dir).unwrap());
+    }
+
+    #[test]
+    fn test_migrations() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let dir = tempdir.path();
+        // An unversioned directory with contents is at version 0.
+        fs::write(dir.join("CURRENT"), b"").unwrap();
+
+        let mut registry = MigrationRegistry::new(3, "test 2.0.0");
+        registry
+            .register(0, "first", append("0"))
+            .register(1, "second", append("1"));
+        assert!(matches!(
+            registry.open_dir(dir, Network::TESTNET),
+            Err(MigrationError::MissingMigration { from_version: 2 })
+        ));
+        assert_eq!(LedgerManifest::load(dir).unwrap().unwrap().format_version, 2);
+
+        // An interrupted step is recorded, and resumed by the next run.
+        let fail = Arc::new(AtomicBool::new(true));
+        let should_fail = fail.clone();
+        registry.register(2, "third", move |dir| match should_fail.load(Ordering::SeqCst) {
+            true => Err("interrupted".into()),
+            false => append("2")(dir),
+        });
+        assert!(matches!(
+            registry.open_dir(dir, Network::TESTNET),
+            Err(MigrationError::Step { from_version: 2, .. })
+        ));
+        assert_eq!(
+            LedgerManifest::load(dir).unwrap().unwrap().pending_migration.as_deref(),
+            Some("third")
+        );
+
+        fail.store(false, Ordering::SeqCst);
+        let manifest = registry.open_dir(dir, Network::TESTNET).unwrap();
+        assert_eq!(manifest.format_version, 3);
+        assert_eq!(manifest.pending_migration, None);
+        assert_eq!(fs::read_to_string(dir.join("steps")).unwrap(), "012");
+    }
+}
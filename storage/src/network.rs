@@ -0,0 +1,245 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    LedgerDirLock,
+    LockError,
+    StorageMode,
+    aleo_ledger_dir,
+    aleo_network_ledger_dir,
+    layout::{development_resources, production_resources},
+};
+
+use std::{fmt, fs, path::PathBuf, str::FromStr};
+
+/// The names of the known networks, indexed by their ID.
+const NETWORK_NAMES: [&str; 3] = ["mainnet", "testnet", "canary"];
+
+///
+/// An Aleo network, identified by its ID.
+///
+/// The known networks are `Network::MAINNET`, `Network::TESTNET` and `Network::CANARY`,
+/// while any other network must be explicitly constructed with `Network::from_id`.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Network(u16);
+
+impl Network {
+    /// The canary network.
+    pub const CANARY: Self = Self(2);
+    /// The main network.
+    pub const MAINNET: Self = Self(0);
+    /// The test network.
+    pub const TESTNET: Self = Self(1);
+
+    /// Returns the network with the given ID, which may be a known or a custom network.
+    pub const fn from_id(id: u16) -> Self {
+        Self(id)
+    }
+
+    /// Returns the network ID.
+    pub const fn id(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns the name of the network, if it is a known network.
+    pub const fn name(&self) -> Option<&'static str> {
+        match self.0 {
+            0 => Some(NETWORK_NAMES[0]),
+            1 => Some(NETWORK_NAMES[1]),
+            2 => Some(NETWORK_NAMES[2]),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the network is a custom network, i.e. not a known one.
+    pub const fn is_custom(&self) -> bool {
+        self.name().is_none()
+    }
+}
+
+impl fmt::Display for Network {
+    /// Formats the network as its name, such as `mainnet`, or as its ID if it is a custom network.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    /// Parses a network from its name, such as `mainnet`, or from its ID.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(id) = NETWORK_NAMES.iter().position(|name| name.eq_ignore_ascii_case(s)) {
+            return Ok(Self(id as u16));
+        }
+        s.parse().map(Self).map_err(|_| format!("unknown network '{s}'"))
+    }
+}
+
+///
+/// Moves the ledger directory of the given network and storage mode from its legacy name, after the network ID
+/// (e.g. `ledger-0`, as given by `aleo_ledger_dir`), to its name after the network (e.g. `ledger-mainnet`,
+/// as given by `aleo_network_ledger_dir`), holding the lock of the ledger directory, and returns the latter.
+///
+/// The resources kept next to the ledger directory and named after the network ID as well are moved along with it,
+/// e.g. `bft-0` and `snapshots-0` to `bft-mainnet` and `snapshots-mainnet`, or every resource of a development node,
+/// e.g. `.bft-0-{id}` and `.node-0-{id}.key`. A resource is left in place if its new location already exists.
+///
+/// Nothing is moved if there is no legacy directory, or if the directory named after the network already exists.
+///
+pub fn migrate_network_ledger_dir(network: Network, mode: &StorageMode) -> Result<PathBuf, LockError> {
+    let ledger_dir = aleo_network_ledger_dir(network, mode);
+    let legacy_dir = aleo_ledger_dir(network.id(), mode);
+    if legacy_dir == ledger_dir || ledger_dir.exists() || !legacy_dir.is_dir() {
+        return Ok(ledger_dir);
+    }
+    let _lock = LedgerDirLock::acquire_dir(&legacy_dir)?;
+    let (legacy_resources, resources) = match mode {
+        StorageMode::Production => (production_resources(&legacy_dir), production_resources(&ledger_dir)),
+        StorageMode::Development(id) => (
+            development_resources(&legacy_dir, &network.id(), *id),
+            development_resources(&ledger_dir, &network, *id),
+        ),
+        StorageMode::Custom(_) | StorageMode::Test(_) => (Vec::new(), Vec::new()),
+    };
+    // The resources are moved before the ledger directory, so that an interrupted migration is resumed by the next one.
+    for (legacy_resource, resource) in legacy_resources.into_iter().zip(resources) {
+        if legacy_resource.symlink_metadata().is_ok() && resource.symlink_metadata().is_err() {
+            fs::rename(&legacy_resource, &resource)?;
+        }
+    }
+    fs::rename(&legacy_dir, &ledger_dir)?;
+    Ok(ledger_dir)
+}
+
+#[cfg(feature = "serde")]
+mod serialize {
+    use super::Network;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+    /// Serializes the network as its name, such as `mainnet`, or as its ID if it is a custom network.
+    impl Serialize for Network {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    /// Deserializes the network from its name, such as `mainnet`, or from its ID.
+    impl<'de> Deserialize<'de> for Network {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network() {
+        assert_eq!(Network::MAINNET.to_string(), "mainnet");
+        assert_eq!(Network::from_id(1), Network::TESTNET);
+        assert_eq!(Network::CANARY.name(), Some("canary"));
+        assert!(Network::from_id(7).is_custom());
+        assert_eq!(Network::from_id(7).to_string(), "7");
+
+        for network in [Network::MAINNET, Network::TESTNET, Network::CANARY, Network::from_id(7)] {
+            assert_eq!(network.to_string().parse::<Network>().unwrap(), network);
+        }
+        assert_eq!("TestNet".parse::<Network>().unwrap(), Network::TESTNET);
+        assert_eq!("2".parse::<Network>().unwrap(), Network::CANARY);
+        assert!("devnet".parse::<Network>().is_err());
+    }
+
+    #[test]
+    fn test_migrate_network_ledger_dir() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let _guard = crate::scoped_aleo_root(tempdir.path());
+        let storage = tempdir.path().join("storage");
+
+        // Nothing is moved without a legacy directory.
+        let ledger_dir = migrate_network_ledger_dir(Network::MAINNET, &StorageMode::Production).unwrap();
+        assert_eq!(ledger_dir, storage.join("ledger-mainnet"));
+        assert!(!ledger_dir.exists());
+
+        fs::create_dir_all(storage.join("ledger-0")).unwrap();
+        fs::write(storage.join("ledger-0").join("CURRENT"), "1").unwrap();
+        assert_eq!(
+            migrate_network_ledger_dir(Network::MAINNET, &StorageMode::Production).unwrap(),
+            ledger_dir
+        );
+        assert_eq!(fs::read_to_string(ledger_dir.join("CURRENT")).unwrap(), "1");
+        assert!(!storage.join("ledger-0").exists());
+
+        // An existing directory named after the network is never replaced.
+        fs::create_dir(storage.join("ledger-0")).unwrap();
+        migrate_network_ledger_dir(Network::MAINNET, &StorageMode::Production).unwrap();
+        assert!(storage.join("ledger-0").exists());
+        assert!(ledger_dir.join("CURRENT").exists());
+
+        // A custom network is always named after its ID.
+        let ledger_dir = migrate_network_ledger_dir(Network::from_id(7), &StorageMode::Production).unwrap();
+        assert_eq!(ledger_dir, storage.join("ledger-7"));
+    }
+
+    #[test]
+    fn test_migrate_network_resources() {
+        let _lock = crate::tests::env_lock();
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let _guard = crate::scoped_aleo_root(tempdir.path());
+        let storage = tempdir.path().join("storage");
+
+        // The BFT state and snapshots of a production ledger are moved along with it.
+        for dir in ["ledger-1", "bft-1", "snapshots-1"] {
+            fs::create_dir_all(storage.join(dir)).unwrap();
+            fs::write(storage.join(dir).join("CURRENT"), dir).unwrap();
+        }
+        // Unless their new location already exists.
+        fs::create_dir(storage.join("snapshots-testnet")).unwrap();
+        migrate_network_ledger_dir(Network::TESTNET, &StorageMode::Production).unwrap();
+        assert_eq!(
+            fs::read_to_string(storage.join("ledger-testnet").join("CURRENT")).unwrap(),
+            "ledger-1"
+        );
+        assert_eq!(
+            fs::read_to_string(storage.join("bft-testnet").join("CURRENT")).unwrap(),
+            "bft-1"
+        );
+        assert!(!storage.join("bft-1").exists());
+        assert!(storage.join("snapshots-1").join("CURRENT").exists());
+        assert!(!storage.join("snapshots-testnet").join("CURRENT").exists());
+
+        // Every resource of a development ledger is moved along with it.
+        let root = tempdir.path().join("repo");
+        crate::set_development_root(&root);
+        fs::create_dir_all(root.join(".ledger-2-4")).unwrap();
+        fs::create_dir(root.join(".bft-2-4")).unwrap();
+        fs::write(root.join(".node-2-4.key"), "key").unwrap();
+        let ledger_dir = migrate_network_ledger_dir(Network::CANARY, &StorageMode::Development(4)).unwrap();
+        assert_eq!(ledger_dir, root.join(".ledger-canary-4"));
+        assert!(ledger_dir.is_dir());
+        assert!(root.join(".bft-canary-4").is_dir());
+        assert_eq!(fs::read_to_string(root.join(".node-canary-4.key")).unwrap(), "key");
+        assert!(!root.join(".bft-2-4").exists() && !root.join(".node-2-4.key").exists());
+        crate::clear_development_root();
+    }
+}
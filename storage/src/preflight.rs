@@ -0,0 +1,349 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Network, StorageMode, aleo_network_ledger_dir, error::is_writable};
+
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+/// The filesystem backing a ledger directory, and whether the directory may be written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreflightReport {
+    /// The directory that was checked.
+    pub path: PathBuf,
+    /// The nearest existing ancestor of the directory, which is the directory itself if it exists.
+    pub existing_path: PathBuf,
+    /// The number of bytes available to the current user.
+    pub free_bytes: u64,
+    /// The total number of bytes of the filesystem.
+    pub total_bytes: u64,
+    /// The filesystem type, such as `ext4` or `tmpfs`, if it could be determined.
+    pub filesystem: Option<String>,
+    /// Whether the filesystem is backed by RAM, such as `tmpfs`.
+    pub is_tmpfs: bool,
+    /// Whether the filesystem is network storage, such as `nfs`.
+    pub is_network: bool,
+    /// Whether the directory may be created or written by the current user.
+    pub is_writable: bool,
+}
+
+impl PreflightReport {
+    ///
+    /// Returns an error if fewer than the given number of bytes are available.
+    ///
+    /// The error is of kind `io::ErrorKind::StorageFull` and states the free and required space.
+    ///
+    pub fn ensure_free_space(&self, min_free_bytes: u64) -> io::Result<()> {
+        if self.free_bytes < min_free_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::StorageFull,
+                format!(
+                    "insufficient disk space for {}: {} bytes free, {} bytes required",
+                    self.path.display(),
+                    self.free_bytes,
+                    min_free_bytes
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    ///
+    /// Returns an error if the directory may not be created or written by the current user.
+    ///
+    pub fn ensure_writable(&self) -> io::Result<()> {
+        if !self.is_writable {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is not writable", self.path.display()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+///
+/// Inspects the filesystem backing the ledger directory of the given network and storage mode.
+///
+/// The directory does not have to exist, in which case its nearest existing ancestor is inspected.
+///
+pub fn preflight(network: Network, mode: &StorageMode) -> io::Result<PreflightReport> {
+    preflight_dir(aleo_network_ledger_dir(network, mode))
+}
+
+///
+/// Inspects the filesystem backing the given directory.
+///
+/// The directory does not have to exist, in which case its nearest existing ancestor is inspected.
+///
+pub fn preflight_dir<P: AsRef<Path>>(path: P) -> io::Result<PreflightReport> {
+    let path = path.as_ref();
+    let existing_path = nearest_existing_ancestor(path)?;
+    let (free_bytes, total_bytes) = filesystem_space(&existing_path)?;
+    let filesystem = filesystem_type(&existing_path);
+    let is_tmpfs = matches!(filesystem.as_deref(), Some("tmpfs" | "ramfs"));
+    let is_network = matches!(
+        filesystem.as_deref(),
+        Some("nfs" | "smb" | "smb2" | "cifs" | "smbfs" | "ceph" | "9p" | "afs" | "afpfs" | "lustre" | "webdav")
+    );
+    let is_writable = is_writable(&existing_path);
+    Ok(PreflightReport {
+        path: path.to_path_buf(),
+        existing_path,
+        free_bytes,
+        total_bytes,
+        filesystem,
+        is_tmpfs,
+        is_network,
+        is_writable,
+    })
+}
+
+/// A change in the free space of a directory, as observed by a `LowSpaceWatcher`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LowSpaceEvent {
+    /// The free space dropped below the threshold.
+    Low(PreflightReport),
+    /// The free space recovered to the threshold or above.
+    Recovered(PreflightReport),
+}
+
+///
+/// A background thread that periodically checks the free space of a directory,
+/// and reports when it drops below, or recovers to, a threshold.
+///
+/// The thread is stopped when the watcher is dropped.
+///
+#[derive(Debug)]
+pub struct LowSpaceWatcher {
+    /// The channel used to stop the thread, which is disconnected when the watcher is dropped.
+    _stop: mpsc::Sender<()>,
+}
+
+impl LowSpaceWatcher {
+    ///
+    /// Starts watching the free space of the given directory every `interval`,
+    /// invoking `on_event` when it drops below `min_free_bytes`, and again when it recovers.
+    ///
+    /// Failures to inspect the directory are skipped, and the check is retried after the interval.
+    ///
+    pub fn spawn<P, F>(path: P, min_free_bytes: u64, interval: Duration, on_event: F) -> io::Result<Self>
+    where
+        P: Into<PathBuf>,
+        F: Fn(LowSpaceEvent) + Send + 'static,
+    {
+        let path = path.into();
+        let (stop, stopped) = mpsc::channel::<()>();
+        thread::Builder::new()
+            .name("aleo-low-space-watcher".to_string())
+            .spawn(move || {
+                let mut is_low = false;
+                loop {
+                    if let Ok(report) = preflight_dir(&path) {
+                        let was_low = std::mem::replace(&mut is_low, report.free_bytes < min_free_bytes);
+                        match (was_low, is_low) {
+                            (false, true) => on_event(LowSpaceEvent::Low(report)),
+                            (true, false) => on_event(LowSpaceEvent::Recovered(report)),
+                            _ => (),
+                        }
+                    }
+                    // Wait for the interval, stopping as soon as the watcher is dropped.
+                    if let Err(mpsc::RecvTimeoutError::Disconnected) | Ok(()) = stopped.recv_timeout(interval) {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self { _stop: stop })
+    }
+}
+
+/// Returns the nearest existing ancestor of the given path, which is the path itself if it exists.
+fn nearest_existing_ancestor(path: &Path) -> io::Result<PathBuf> {
+    let mut existing = path;
+    loop {
+        match fs::symlink_metadata(existing) {
+            Ok(_) => return Ok(existing.to_path_buf()),
+            Err(error) if matches!(error.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) => {
+                existing = match existing.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent,
+                    // A relative path is resolved against the current working directory.
+                    Some(_) if existing != Path::new(".") => Path::new("."),
+                    _ => return Err(error),
+                };
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Returns the number of bytes available to the current user, and the total number of bytes, of the filesystem.
+#[cfg(unix)]
+fn filesystem_space(path: &Path) -> io::Result<(u64, u64)> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // Safety: the path is a valid C string, and the buffer is valid for writes of a `statvfs`.
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Safety: `statvfs` succeeded, so the buffer is initialized.
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    let (fragment_size, available, blocks) = (stat.f_frsize as u64, stat.f_bavail as u64, stat.f_blocks as u64);
+    Ok((
+        available.saturating_mul(fragment_size),
+        blocks.saturating_mul(fragment_size),
+    ))
+}
+
+/// Returns the number of bytes available to the current user, and the total number of bytes, of the filesystem.
+#[cfg(not(unix))]
+fn filesystem_space(_path: &Path) -> io::Result<(u64, u64)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "disk space checks are not supported on this platform",
+    ))
+}
+
+/// Returns the type of the filesystem, if it is known.
+#[cfg(target_os = "linux")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    // Safety: the path is a valid C string, and the buffer is valid for writes of a `statfs`.
+    if unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // Safety: `statfs` succeeded, so the buffer is initialized.
+    let stat = unsafe { stat.assume_init() };
+    // The magic numbers are listed in `linux/magic.h`.
+    #[allow(clippy::unnecessary_cast)]
+    let name = match stat.f_type as u64 {
+        0xEF53 => "ext4",
+        0x58465342 => "xfs",
+        0x9123683E => "btrfs",
+        0x2FC12FC1 => "zfs",
+        0xF2F52010 => "f2fs",
+        0x01021994 => "tmpfs",
+        0x858458F6 => "ramfs",
+        0x794C7630 => "overlay",
+        0x65735546 => "fuse",
+        0x6969 => "nfs",
+        0x517B => "smb",
+        0xFE534D42 => "smb2",
+        0xFF534D42 => "cifs",
+        0x00C36400 => "ceph",
+        0x01021997 => "9p",
+        0x5346414F => "afs",
+        0x0BD00BD0 => "lustre",
+        0x5346544E => "ntfs",
+        0x4D44 => "vfat",
+        0x2011BAB0 => "exfat",
+        other => return Some(format!("{other:#x}")),
+    };
+    Some(name.to_string())
+}
+
+/// Returns the type of the filesystem, if it is known.
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "openbsd"))]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::{
+        ffi::{CStr, CString},
+        os::unix::ffi::OsStrExt,
+    };
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    // Safety: the path is a valid C string, and the buffer is valid for writes of a `statfs`.
+    if unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // Safety: `statfs` succeeded, so the buffer is initialized, and the type name is nul-terminated.
+    let name = unsafe { CStr::from_ptr(stat.assume_init().f_fstypename.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// Returns the type of the filesystem, if it is known.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd"
+)))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight() {
+        let mode = StorageMode::new_test(None);
+        let report = preflight(Network::MAINNET, &mode).unwrap();
+        assert_eq!(report.path, report.existing_path);
+        assert!(report.total_bytes >= report.free_bytes);
+        assert!(report.is_writable);
+        report.ensure_writable().unwrap();
+        report.ensure_free_space(0).unwrap();
+        assert_eq!(
+            report.ensure_free_space(u64::MAX).unwrap_err().kind(),
+            io::ErrorKind::StorageFull
+        );
+
+        // A missing directory is checked through its nearest existing ancestor.
+        let missing = report.path.join("a").join("b");
+        let report = preflight_dir(&missing).unwrap();
+        assert_eq!(report.path, missing);
+        assert_eq!(report.existing_path, missing.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn test_low_space_watcher() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let watcher = LowSpaceWatcher::spawn(tempdir.path(), u64::MAX, Duration::from_millis(10), move |event| {
+            let _ = sender.send(event);
+        })
+        .unwrap();
+
+        // The free space is reported as low once, rather than on every check.
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Ok(LowSpaceEvent::Low(_))
+        ));
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+
+        // The thread stops, disconnecting the channel, once the watcher is dropped.
+        drop(watcher);
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        );
+    }
+}
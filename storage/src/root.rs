@@ -0,0 +1,178 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{cell::RefCell, marker::PhantomData, path::PathBuf, sync::RwLock};
+
+/// The environment variable that overrides the Aleo directory.
+pub const ALEO_HOME_ENV: &str = "ALEO_HOME";
+
+/// The process-wide Aleo root, set via `set_aleo_root`.
+static ALEO_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+thread_local! {
+    /// The stack of scoped Aleo roots for the current thread, pushed via `scoped_aleo_root`.
+    static SCOPED_ALEO_ROOTS: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+///
+/// Sets the process-wide Aleo root, which replaces `~/.aleo` for every path helper.
+///
+/// The root takes precedence over the `ALEO_HOME` environment variable,
+/// but is itself shadowed by any active `scoped_aleo_root` on the calling thread.
+///
+pub fn set_aleo_root<P: Into<PathBuf>>(root: P) {
+    *ALEO_ROOT.write().unwrap_or_else(|e| e.into_inner()) = Some(root.into());
+}
+
+///
+/// Clears the process-wide Aleo root, restoring the default resolution.
+///
+pub fn clear_aleo_root() {
+    *ALEO_ROOT.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+///
+/// Overrides the Aleo root for the current thread until the returned guard is dropped.
+///
+/// Scoped roots nest, with the innermost one taking precedence over every other source.
+///
+pub fn scoped_aleo_root<P: Into<PathBuf>>(root: P) -> AleoRootGuard {
+    let depth = SCOPED_ALEO_ROOTS.with(|roots| {
+        let mut roots = roots.borrow_mut();
+        roots.push(root.into());
+        roots.len() - 1
+    });
+    AleoRootGuard {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+///
+/// A guard that restores the previous Aleo root of the current thread when dropped.
+///
+/// Dropping a guard also removes the roots scoped after it, even if their guards are still alive,
+/// so that guards dropped out of order never leave an outer root in place of an inner one.
+///
+#[must_use = "the scoped Aleo root is removed as soon as the guard is dropped"]
+pub struct AleoRootGuard {
+    /// The number of roots scoped before this one on the current thread.
+    depth: usize,
+    // The guard manipulates thread-local state, so it must be dropped on the thread that created it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for AleoRootGuard {
+    fn drop(&mut self) {
+        SCOPED_ALEO_ROOTS.with(|roots| roots.borrow_mut().truncate(self.depth));
+    }
+}
+
+///
+/// Returns the overridden Aleo root, if any, in order of precedence:
+/// the innermost scoped root, the process-wide root, and then the `ALEO_HOME` environment variable.
+///
+pub(crate) fn aleo_root_override() -> Option<PathBuf> {
+    if let Some(root) = SCOPED_ALEO_ROOTS.with(|roots| roots.borrow().last().cloned()) {
+        return Some(root);
+    }
+    if let Some(root) = ALEO_ROOT.read().unwrap_or_else(|e| e.into_inner()).clone() {
+        return Some(root);
+    }
+    match std::env::var_os(ALEO_HOME_ENV) {
+        Some(root) if !root.is_empty() => Some(PathBuf::from(root)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LayoutPolicy,
+        StorageMode,
+        aleo_dir,
+        aleo_ledger_dir,
+        clear_layout_policy,
+        set_layout_policy,
+        tests::env_lock,
+    };
+
+    #[test]
+    fn test_scoped_aleo_root() {
+        let outer = scoped_aleo_root("/outer");
+        assert_eq!(aleo_dir(), PathBuf::from("/outer"));
+        {
+            let _inner = scoped_aleo_root("/inner");
+            assert_eq!(aleo_dir(), PathBuf::from("/inner"));
+            assert_eq!(
+                aleo_ledger_dir(1, &StorageMode::Production),
+                PathBuf::from("/inner/storage/ledger-1")
+            );
+        }
+        assert_eq!(aleo_dir(), PathBuf::from("/outer"));
+        drop(outer);
+        assert_ne!(aleo_dir(), PathBuf::from("/outer"));
+    }
+
+    #[test]
+    fn test_scoped_aleo_root_out_of_order() {
+        let outer = scoped_aleo_root("/outer");
+        let inner = scoped_aleo_root("/inner");
+
+        // Dropping the outer guard first removes both roots, rather than leaving the outer one in place.
+        drop(outer);
+        assert_ne!(aleo_dir(), PathBuf::from("/outer"));
+        assert_ne!(aleo_dir(), PathBuf::from("/inner"));
+        drop(inner);
+        assert_ne!(aleo_dir(), PathBuf::from("/outer"));
+
+        // The roots scoped afterwards are unaffected by the guards dropped before.
+        let _guard = scoped_aleo_root("/after");
+        assert_eq!(aleo_dir(), PathBuf::from("/after"));
+    }
+
+    #[test]
+    fn test_aleo_root_precedence() {
+        let _lock = env_lock();
+        // The default directory depends on the layout policy, which is pinned to the legacy layout.
+        set_layout_policy(LayoutPolicy::Legacy);
+        let default_dir = dirs::home_dir().unwrap().join(crate::ALEO_DIRECTORY);
+
+        std::env::set_var(ALEO_HOME_ENV, "/from/env");
+        assert_eq!(aleo_dir(), PathBuf::from("/from/env"));
+
+        set_aleo_root("/from/api");
+        assert_eq!(aleo_dir(), PathBuf::from("/from/api"));
+
+        {
+            let _guard = scoped_aleo_root("/from/scope");
+            assert_eq!(aleo_dir(), PathBuf::from("/from/scope"));
+        }
+        assert_eq!(aleo_dir(), PathBuf::from("/from/api"));
+
+        clear_aleo_root();
+        assert_eq!(aleo_dir(), PathBuf::from("/from/env"));
+
+        // An empty environment variable is treated as unset.
+        std::env::set_var(ALEO_HOME_ENV, "");
+        assert_eq!(aleo_dir(), default_dir);
+
+        std::env::remove_var(ALEO_HOME_ENV);
+        assert_eq!(aleo_dir(), default_dir);
+        clear_layout_policy();
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{StorageMode, check_custom_path};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+
+///
+/// Serializes the storage mode as a string, e.g. `dev:3`; see `Display` for `StorageMode`.
+///
+/// A custom mode with an empty or non-UTF-8 path is an error, as it could not be deserialized.
+///
+impl Serialize for StorageMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let StorageMode::Custom(path) = self {
+            check_custom_path(path).map_err(ser::Error::custom)?;
+        }
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes the storage mode from a string, e.g. `dev:3`; see `FromStr` for `StorageMode`.
+impl<'de> Deserialize<'de> for StorageMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_storage_mode() {
+        let mode = StorageMode::Custom("/data/ledger".into());
+        let json = serde_json::to_string(&mode).unwrap();
+        assert_eq!(json, r#""custom:/data/ledger""#);
+        assert_eq!(serde_json::from_str::<StorageMode>(&json).unwrap(), mode);
+
+        assert_eq!(
+            serde_json::from_str::<StorageMode>(r#""dev:7""#).unwrap(),
+            StorageMode::Development(7)
+        );
+        assert!(serde_json::from_str::<StorageMode>(r#""staging""#).is_err());
+
+        // A custom mode that could not be deserialized is not serialized.
+        assert!(serde_json::to_string(&StorageMode::Custom("".into())).is_err());
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+            let mode = StorageMode::Custom(OsStr::from_bytes(b"/data/ledger-\xff").into());
+            assert!(serde_json::to_string(&mode).is_err());
+        }
+    }
+}
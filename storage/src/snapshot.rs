@@ -0,0 +1,654 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    LEDGER_LOCK_FILE,
+    LedgerDirLock,
+    LockError,
+    Network,
+    StorageLayout,
+    StorageMode,
+    atomic::{TEMP_FILE_MARKER, sync_dir},
+    write_atomic,
+};
+
+use std::{
+    ffi::OsStr,
+    fmt,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the metadata file within a snapshot.
+pub const SNAPSHOT_METADATA_FILE: &str = ".aleo-snapshot";
+
+/// The name of the directory within the ledger directory in which its new contents are staged while they are restored.
+pub(crate) const RESTORE_STAGING_DIR: &str = ".aleo-restore";
+
+/// The name of the directory within the ledger directory into which its previous contents are moved by a restore.
+const RESTORE_REPLACING_DIR: &str = ".aleo-restore-replacing";
+
+/// The name the directory of the previous contents is renamed to once all of them were moved into it.
+const RESTORE_REPLACED_DIR: &str = ".aleo-restore-replaced";
+
+/// The extensions of the files that are never modified once written, and may therefore be shared by hard links.
+const IMMUTABLE_EXTENSIONS: [&str; 2] = ["sst", "blob"];
+
+/// An error that occurs when creating, removing or restoring a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The ledger directory could not be locked.
+    Lock(LockError),
+    /// An I/O error occurred while accessing the ledger directory or the snapshots.
+    Io(io::Error),
+    /// The snapshot name is empty, starts with a `.`, or contains a character other than `A-Za-z0-9._-`.
+    InvalidName(String),
+    /// A snapshot with the given name already exists.
+    AlreadyExists(String),
+    /// No snapshot with the given name exists.
+    NotFound(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lock(error) => write!(f, "{error}"),
+            Self::Io(error) => write!(f, "failed to access the snapshot: {error}"),
+            Self::InvalidName(name) => write!(f, "'{name}' is not a valid snapshot name"),
+            Self::AlreadyExists(name) => write!(f, "the snapshot '{name}' already exists"),
+            Self::NotFound(name) => write!(f, "the snapshot '{name}' does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Lock(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::InvalidName(_) | Self::AlreadyExists(_) | Self::NotFound(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<LockError> for SnapshotError {
+    fn from(error: LockError) -> Self {
+        Self::Lock(error)
+    }
+}
+
+/// A point-in-time copy of a ledger directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The name of the snapshot.
+    pub name: String,
+    /// The directory of the snapshot.
+    pub path: PathBuf,
+    /// The network of the ledger.
+    pub network: Network,
+    /// The time the snapshot was created, in seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+impl Snapshot {
+    /// Loads the snapshot in the given directory, returning `None` if it has no valid metadata file.
+    fn load(path: PathBuf) -> io::Result<Option<Self>> {
+        let Some(name) = path.file_name().and_then(OsStr::to_str).map(str::to_string) else {
+            return Ok(None);
+        };
+        let contents = match fs::read_to_string(path.join(SNAPSHOT_METADATA_FILE)) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let (mut network, mut created_at) = (None, None);
+        for line in contents.lines() {
+            match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("network", value)) => network = value.parse().ok().map(Network::from_id),
+                Some(("created_at", value)) => created_at = value.parse().ok(),
+                _ => continue,
+            }
+        }
+        Ok(network.zip(created_at).map(|(network, created_at)| Self {
+            name,
+            path,
+            network,
+            created_at,
+        }))
+    }
+}
+
+///
+/// The snapshots of the ledger directory of a given network and storage mode.
+///
+/// A snapshot is created by reflinking the files of the ledger where the filesystem supports it.
+/// Otherwise, the files that are never modified once written, such as RocksDB's `.sst` files, are hard-linked,
+/// and any other file is copied. The lock file of the ledger is never part of a snapshot.
+///
+/// The other resources of the node, such as its private key, are kept next to the ledger directory (see `StorageLayout`),
+/// so they are neither part of a snapshot, nor replaced when one is restored: only the ledger data is.
+///
+#[derive(Clone, Debug)]
+pub struct SnapshotStore {
+    /// The network of the ledger.
+    network: Network,
+    /// The ledger directory.
+    ledger_dir: PathBuf,
+    /// The directory holding the snapshots.
+    snapshots_dir: PathBuf,
+    /// The paths that are not part of the ledger's contents, such as the snapshots, if they are nested in the ledger directory.
+    excluded: Vec<PathBuf>,
+}
+
+impl SnapshotStore {
+    /// Initializes the snapshots of the ledger directory of the given network and storage mode.
+    pub fn new(network: Network, mode: &StorageMode) -> Self {
+        let layout = StorageLayout::new(network, mode.clone());
+        Self::with_dirs(network, layout.ledger_dir(), layout.snapshots_dir())
+    }
+
+    /// Initializes the snapshots of the given ledger directory, held in the given directory.
+    pub fn with_dirs<P: Into<PathBuf>, Q: Into<PathBuf>>(network: Network, ledger_dir: P, snapshots_dir: Q) -> Self {
+        let snapshots_dir = snapshots_dir.into();
+        Self {
+            network,
+            ledger_dir: ledger_dir.into(),
+            excluded: vec![snapshots_dir.clone()],
+            snapshots_dir,
+        }
+    }
+
+    /// Returns the ledger directory.
+    pub fn ledger_dir(&self) -> &Path {
+        &self.ledger_dir
+    }
+
+    /// Returns the directory holding the snapshots.
+    pub fn snapshots_dir(&self) -> &Path {
+        &self.snapshots_dir
+    }
+
+    ///
+    /// Creates a snapshot of the ledger directory with the given name, holding the lock of the ledger directory.
+    ///
+    /// Returns `SnapshotError::Lock` if the ledger directory is in use, as its contents would be inconsistent.
+    ///
+    pub fn create(&self, name: &str) -> Result<Snapshot, SnapshotError> {
+        validate_name(name)?;
+        let _lock = LedgerDirLock::acquire_dir(&self.ledger_dir)?;
+        let path = self.snapshots_dir.join(name);
+        if path.exists() {
+            return Err(SnapshotError::AlreadyExists(name.to_string()));
+        }
+
+        // The snapshot is staged under a hidden name, so an interrupted snapshot is never listed.
+        let staging = self.snapshots_dir.join(format!(".{name}.partial"));
+        remove_path(&staging)?;
+        fs::create_dir_all(&self.snapshots_dir)?;
+        copy_tree(&self.ledger_dir, &staging, &|entry| {
+            is_ledger_entry(entry, &self.excluded)
+        })?;
+
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        write_atomic(
+            staging.join(SNAPSHOT_METADATA_FILE),
+            format!("network = {}\ncreated_at = {created_at}\n", self.network.id()),
+        )?;
+        fs::rename(&staging, &path)?;
+        sync_dir(&self.snapshots_dir)?;
+
+        Ok(Snapshot {
+            name: name.to_string(),
+            path,
+            network: self.network,
+            created_at,
+        })
+    }
+
+    /// Returns the snapshots of the ledger directory, from the oldest to the newest.
+    pub fn list(&self) -> Result<Vec<Snapshot>, SnapshotError> {
+        let entries = match fs::read_dir(&self.snapshots_dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if validate_name(&entry.file_name().to_string_lossy()).is_ok() && entry.file_type()?.is_dir() {
+                snapshots.extend(Snapshot::load(entry.path())?.filter(|s| s.network == self.network));
+            }
+        }
+        snapshots.sort_by(|a, b| (a.created_at, &a.name).cmp(&(b.created_at, &b.name)));
+        Ok(snapshots)
+    }
+
+    /// Returns the snapshot with the given name.
+    pub fn get(&self, name: &str) -> Result<Snapshot, SnapshotError> {
+        validate_name(name)?;
+        Snapshot::load(self.snapshots_dir.join(name))?
+            .filter(|snapshot| snapshot.network == self.network)
+            .ok_or_else(|| SnapshotError::NotFound(name.to_string()))
+    }
+
+    /// Removes the snapshot with the given name.
+    pub fn remove(&self, name: &str) -> Result<(), SnapshotError> {
+        let snapshot = self.get(name)?;
+        fs::remove_dir_all(&snapshot.path)?;
+        Ok(sync_dir(&self.snapshots_dir)?)
+    }
+
+    /// Removes all but the given number of newest snapshots, and returns the removed snapshots.
+    pub fn prune(&self, keep: usize) -> Result<Vec<Snapshot>, SnapshotError> {
+        let mut snapshots = self.list()?;
+        let count = snapshots.len().saturating_sub(keep);
+        snapshots.truncate(count);
+        for snapshot in &snapshots {
+            fs::remove_dir_all(&snapshot.path)?;
+        }
+        if !snapshots.is_empty() {
+            sync_dir(&self.snapshots_dir)?;
+        }
+        Ok(snapshots)
+    }
+
+    ///
+    /// Replaces the contents of the ledger directory with the snapshot of the given name,
+    /// holding the lock of the ledger directory.
+    ///
+    /// The snapshot is first copied next to the current contents, which are then moved aside and replaced.
+    /// If the restore is interrupted, it is rolled back or completed the next time the ledger directory
+    /// is locked (see `LedgerDirLock`), so the ledger never holds a mix of both.
+    ///
+    pub fn restore(&self, name: &str) -> Result<Snapshot, SnapshotError> {
+        let snapshot = self.get(name)?;
+        let _lock = LedgerDirLock::acquire_dir(&self.ledger_dir)?;
+
+        let staging = self.ledger_dir.join(RESTORE_STAGING_DIR);
+        let staged = copy_tree(&snapshot.path, &staging, &|entry| {
+            entry.file_name() != SNAPSHOT_METADATA_FILE
+                && is_ledger_path(&self.ledger_dir.join(entry.file_name()), &self.excluded)
+        });
+        if let Err(error) = staged {
+            remove_path(&staging)?;
+            return Err(error.into());
+        }
+
+        replace_ledger_contents(&self.ledger_dir, &self.excluded)?;
+        Ok(snapshot)
+    }
+}
+
+/// Returns `true` if the given entry of a ledger directory is part of the ledger's contents.
+pub(crate) fn is_ledger_entry(entry: &fs::DirEntry, excluded: &[PathBuf]) -> bool {
+    is_ledger_path(&entry.path(), excluded)
+}
+
+///
+/// Returns `true` if the given path, directly within a ledger directory, is part of the ledger's contents,
+/// i.e. it is neither the lock file, a directory of a restore, a temporary file, nor one of the excluded paths.
+///
+pub(crate) fn is_ledger_path(path: &Path, excluded: &[PathBuf]) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    name != LEDGER_LOCK_FILE
+        && !is_restore_dir(name)
+        && !name.to_string_lossy().contains(TEMP_FILE_MARKER)
+        && !excluded.iter().any(|excluded| excluded == path)
+}
+
+/// Returns `true` if the given name, directly within a ledger directory, is one of the directories of a restore.
+pub(crate) fn is_restore_dir(name: &OsStr) -> bool {
+    name == RESTORE_STAGING_DIR || name == RESTORE_REPLACING_DIR || name == RESTORE_REPLACED_DIR
+}
+
+///
+/// Replaces the contents of the locked ledger directory with the entries of its restore staging directory.
+///
+/// The previous contents are first moved aside, and only then replaced by the staged entries, so that an interrupted
+/// replacement is rolled back or completed by `recover_restore`, depending on whether every previous entry was moved.
+///
+pub(crate) fn replace_ledger_contents(ledger_dir: &Path, excluded: &[PathBuf]) -> io::Result<()> {
+    let replacing = ledger_dir.join(RESTORE_REPLACING_DIR);
+    let replaced = ledger_dir.join(RESTORE_REPLACED_DIR);
+    fs::create_dir(&replacing)?;
+    for entry in fs::read_dir(ledger_dir)? {
+        let entry = entry?;
+        if is_ledger_entry(&entry, excluded) {
+            fs::rename(entry.path(), replacing.join(entry.file_name()))?;
+        }
+    }
+    sync_dir(&replacing)?;
+    fs::rename(&replacing, &replaced)?;
+    sync_dir(ledger_dir)?;
+
+    move_entries(&ledger_dir.join(RESTORE_STAGING_DIR), ledger_dir)?;
+    remove_path(&replaced)?;
+    sync_dir(ledger_dir)
+}
+
+///
+/// Recovers the locked ledger directory from an interrupted restore, if any, and returns `true` if it did.
+///
+/// A restore that moved every previous entry aside is completed with the staged entries. Otherwise, the entries
+/// moved aside are moved back, and the staged entries, which may be incomplete, are removed.
+///
+pub(crate) fn recover_restore(ledger_dir: &Path) -> io::Result<bool> {
+    let staging = ledger_dir.join(RESTORE_STAGING_DIR);
+    let replacing = ledger_dir.join(RESTORE_REPLACING_DIR);
+    let replaced = ledger_dir.join(RESTORE_REPLACED_DIR);
+    if replaced.symlink_metadata().is_ok() {
+        move_entries(&staging, ledger_dir)?;
+        remove_path(&replaced)?;
+    } else if replacing.symlink_metadata().is_ok() {
+        move_entries(&replacing, ledger_dir)?;
+        remove_path(&staging)?;
+    } else if staging.symlink_metadata().is_ok() {
+        remove_path(&staging)?;
+    } else {
+        return Ok(false);
+    }
+    sync_dir(ledger_dir)?;
+    Ok(true)
+}
+
+/// Moves the entries of the given directory, if it exists, into the target directory, and removes it.
+fn move_entries(source: &Path, target: &Path) -> io::Result<()> {
+    let entries = match fs::read_dir(source) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for entry in entries {
+        let entry = entry?;
+        fs::rename(entry.path(), target.join(entry.file_name()))?;
+    }
+    fs::remove_dir(source)
+}
+
+/// Returns an error if the given snapshot name is empty, starts with a `.`, or contains a character other than `A-Za-z0-9._-`.
+fn validate_name(name: &str) -> Result<(), SnapshotError> {
+    let is_valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    match is_valid {
+        true => Ok(()),
+        false => Err(SnapshotError::InvalidName(name.to_string())),
+    }
+}
+
+/// Removes the given file or directory, if it exists.
+pub(crate) fn remove_path(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(error) => Err(error),
+    };
+    match result {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Copies the entries of the source directory accepted by the filter, and everything beneath them, to a new directory.
+fn copy_tree(source: &Path, target: &Path, filter: &dyn Fn(&fs::DirEntry) -> bool) -> io::Result<()> {
+    fs::create_dir(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        if !filter(&entry) {
+            continue;
+        }
+        let target_path = target.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&entry.path(), &target_path, &|_| true)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target_path)?;
+        } else {
+            copy_file(&entry.path(), &target_path)?;
+        }
+    }
+    sync_dir(target)
+}
+
+/// Copies the given file, by reflinking it, hard-linking it if it is immutable, or copying it, in that order of preference.
+fn copy_file(source: &Path, target: &Path) -> io::Result<()> {
+    if reflink(source, target).is_ok() {
+        return Ok(());
+    }
+    let is_immutable = source
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| IMMUTABLE_EXTENSIONS.contains(&extension));
+    if is_immutable && fs::hard_link(source, target).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, target)?;
+    File::open(target)?.sync_all()
+}
+
+/// Clones the given file into a new file sharing its data, if the filesystem supports it (e.g. Btrfs or XFS).
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source_file = File::open(source)?;
+    let target_file = File::create_new(target)?;
+    // SAFETY: Both file descriptors are open for the duration of the call.
+    let result = unsafe { libc::ioctl(target_file.as_raw_fd(), libc::FICLONE, source_file.as_raw_fd()) };
+    let result = match result {
+        0 => target_file
+            .set_permissions(source_file.metadata()?.permissions())
+            .and_then(|()| target_file.sync_all()),
+        _ => Err(io::Error::last_os_error()),
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+/// Clones the given file into a new file sharing its data, if the filesystem supports it.
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Copies the given symbolic link, rather than its target.
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(fs::read_link(source)?, target);
+    #[cfg(not(unix))]
+    copy_file(source, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the given files of a ledger, creating their directories.
+    fn write_ledger(dir: &Path, files: &[(&str, &str)]) {
+        for (name, contents) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let mode = StorageMode::new_test(None);
+        let store = SnapshotStore::new(Network::TESTNET, &mode);
+        // The snapshots are kept next to the ledger directory.
+        assert_eq!(store.snapshots_dir().parent(), store.ledger_dir().parent());
+        assert!(store.list().unwrap().is_empty());
+
+        write_ledger(store.ledger_dir(), &[("CURRENT", "1"), ("db/000001.sst", "blocks")]);
+        let first = store.create("before-upgrade").unwrap();
+        assert_eq!(store.get("before-upgrade").unwrap(), first);
+        assert!(!first.path.join(LEDGER_LOCK_FILE).exists());
+        assert!(matches!(
+            store.create("before-upgrade"),
+            Err(SnapshotError::AlreadyExists(_))
+        ));
+        assert!(matches!(store.create("../escape"), Err(SnapshotError::InvalidName(_))));
+
+        write_ledger(store.ledger_dir(), &[
+            ("CURRENT", "2"),
+            ("db/000002.sst", "more blocks"),
+        ]);
+        store.create("after-upgrade").unwrap();
+        let names = |snapshots: Vec<Snapshot>| snapshots.into_iter().map(|s| s.name).collect::<Vec<_>>();
+        let mut listed = names(store.list().unwrap());
+        listed.sort();
+        assert_eq!(listed, ["after-upgrade", "before-upgrade"]);
+
+        // Restoring replaces the contents of the ledger directory.
+        store.restore("before-upgrade").unwrap();
+        assert_eq!(fs::read_to_string(store.ledger_dir().join("CURRENT")).unwrap(), "1");
+        assert_eq!(
+            fs::read_to_string(store.ledger_dir().join("db/000001.sst")).unwrap(),
+            "blocks"
+        );
+        assert!(!store.ledger_dir().join("db/000002.sst").exists());
+        assert!(!store.ledger_dir().join(SNAPSHOT_METADATA_FILE).exists());
+        assert_eq!(store.list().unwrap().len(), 2);
+
+        assert_eq!(store.prune(1).unwrap().len(), 1);
+        assert_eq!(store.list().unwrap().len(), 1);
+        store.remove(&store.list().unwrap()[0].name).unwrap();
+        assert!(matches!(
+            store.restore("after-upgrade"),
+            Err(SnapshotError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_interrupted_restore() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let ledger_dir = tempdir.path().join("ledger");
+        let entries = |dir: &Path| {
+            let mut entries = fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            entries.sort();
+            entries
+        };
+
+        // A restore interrupted while staging the new contents is abandoned.
+        write_ledger(&ledger_dir, &[("CURRENT", "1"), (".aleo-restore/CURRENT", "2")]);
+        drop(LedgerDirLock::acquire_dir(&ledger_dir).unwrap());
+        assert_eq!(entries(&ledger_dir), [LEDGER_LOCK_FILE, "CURRENT"]);
+        assert_eq!(fs::read_to_string(ledger_dir.join("CURRENT")).unwrap(), "1");
+
+        // A restore interrupted while moving the previous contents aside is rolled back.
+        write_ledger(&ledger_dir, &[
+            (".aleo-restore-replacing/000001.sst", "1"),
+            (".aleo-restore/CURRENT", "2"),
+            (".aleo-restore/000002.sst", "2"),
+        ]);
+        drop(LedgerDirLock::acquire_dir(&ledger_dir).unwrap());
+        assert_eq!(entries(&ledger_dir), [LEDGER_LOCK_FILE, "000001.sst", "CURRENT"]);
+        assert_eq!(fs::read_to_string(ledger_dir.join("CURRENT")).unwrap(), "1");
+
+        // A restore interrupted while moving the new contents in is completed.
+        fs::remove_file(ledger_dir.join("000001.sst")).unwrap();
+        fs::rename(ledger_dir.join("CURRENT"), tempdir.path().join("CURRENT")).unwrap();
+        write_ledger(&ledger_dir, &[
+            ("CURRENT", "2"),
+            (".aleo-restore/000002.sst", "2"),
+            (".aleo-restore-replaced/000001.sst", "1"),
+            (".aleo-restore-replaced/CURRENT", "1"),
+        ]);
+        drop(LedgerDirLock::acquire_dir(&ledger_dir).unwrap());
+        assert_eq!(entries(&ledger_dir), [LEDGER_LOCK_FILE, "000002.sst", "CURRENT"]);
+        assert_eq!(fs::read_to_string(ledger_dir.join("CURRENT")).unwrap(), "2");
+        assert!(!recover_restore(&ledger_dir).unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_custom_mode() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let mode = StorageMode::Custom(tempdir.path().join("node"));
+        let layout = StorageLayout::new(Network::MAINNET, mode.clone());
+        let store = SnapshotStore::new(Network::MAINNET, &mode);
+        write_ledger(store.ledger_dir(), &[("CURRENT", "1")]);
+        write_ledger(tempdir.path(), &[
+            (".node-node.key", "APrivateKey1"),
+            (".peers-node", "127.0.0.1:4130"),
+            (".config-node.toml", "[rest]"),
+            (".logs-node/node.log", "started"),
+            (".bft-node/state", "1"),
+            (".resources-node/proving-keys-mainnet/credits.prover", "key"),
+        ]);
+        let snapshot = store.create("offline").unwrap();
+
+        // Only the ledger data is part of the snapshot.
+        let mut entries = fs::read_dir(&snapshot.path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, [SNAPSHOT_METADATA_FILE, "CURRENT"]);
+
+        // Restoring leaves the other resources of the node untouched.
+        write_ledger(store.ledger_dir(), &[("CURRENT", "2")]);
+        write_ledger(tempdir.path(), &[(".node-node.key", "APrivateKey2")]);
+        store.restore("offline").unwrap();
+        assert_eq!(fs::read_to_string(store.ledger_dir().join("CURRENT")).unwrap(), "1");
+        assert_eq!(fs::read_to_string(layout.node_key_path()).unwrap(), "APrivateKey2");
+        assert!(layout.peers_path().exists());
+        assert!(layout.config_path().exists());
+        assert!(layout.logs_dir().join("node.log").exists());
+        assert!(layout.bft_dir().join("state").exists());
+        assert!(layout.proving_keys_dir().join("credits.prover").exists());
+    }
+
+    #[test]
+    fn test_snapshot_locked_ledger() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let store = SnapshotStore::with_dirs(
+            Network::MAINNET,
+            tempdir.path().join("ledger"),
+            tempdir.path().join("snapshots"),
+        );
+        write_ledger(store.ledger_dir(), &[("CURRENT", "1")]);
+
+        let lock = LedgerDirLock::acquire_dir(store.ledger_dir()).unwrap();
+        assert!(matches!(store.create("live"), Err(SnapshotError::Lock(_))));
+        drop(lock);
+        store.create("offline").unwrap();
+
+        // Snapshots of another network are not listed.
+        let other = SnapshotStore::with_dirs(Network::TESTNET, store.ledger_dir(), store.snapshots_dir());
+        assert!(other.list().unwrap().is_empty());
+    }
+}
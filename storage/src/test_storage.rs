@@ -0,0 +1,722 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    StorageMode,
+    TEST_DIR_PREFIX,
+    discovery::dir_usage,
+    layout::custom_resources,
+    preflight_dir,
+    snapshot::remove_path,
+};
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+use tempfile::TempDir;
+
+/// The environment variable that keeps every test storage directory, if it is set to a value other than `0` or `false`.
+pub const ALEO_KEEP_TEST_DIR_ENV: &str = "ALEO_KEEP_TEST_DIR";
+
+/// The environment variable that overrides the RAM-backed directory in which test storage directories are created.
+pub const ALEO_TEST_RAM_DIR_ENV: &str = "ALEO_TEST_RAM_DIR";
+
+/// The default RAM-backed directory, which is only used if it is a tmpfs.
+const DEFAULT_RAM_DIR: &str = "/dev/shm";
+
+/// How often the usage of a test storage directory with a quota is measured by default.
+const DEFAULT_QUOTA_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How a test storage directory is seeded from a fixture directory.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// The files of the fixture are copied.
+    #[default]
+    Copy,
+    /// The files of the fixture are hard-linked, or copied if they are on another filesystem.
+    /// The test must not modify the files in place, as that would modify the fixture too.
+    HardLink,
+}
+
+///
+/// A builder for a `TestStorage`.
+///
+/// By default, the directory has a random name, is empty, is created in the system's temporary directory,
+/// and is kept when the test panics.
+///
+#[derive(Clone, Debug)]
+pub struct TestStorageBuilder {
+    /// The name of the directory, which is random if `None`.
+    name: Option<String>,
+    /// The fixture directory to seed the directory from, and how.
+    fixture: Option<(PathBuf, FixtureMode)>,
+    /// The space the test needs, if the directory should be RAM-backed.
+    ram_backed: Option<u64>,
+    /// The maximum size of the directory, if any.
+    quota: Option<u64>,
+    /// How often the usage of the directory is measured, if it has a quota.
+    quota_check_interval: Duration,
+    /// Whether to keep the directory if the `TestStorage` is dropped while panicking.
+    keep_on_panic: bool,
+    /// Whether to always keep the directory.
+    keep: bool,
+}
+
+impl Default for TestStorageBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestStorageBuilder {
+    /// Initializes a new builder, which always keeps the directory if the `ALEO_KEEP_TEST_DIR` environment variable is set.
+    pub fn new() -> Self {
+        let keep =
+            std::env::var(ALEO_KEEP_TEST_DIR_ENV).is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"));
+        Self {
+            name: None,
+            fixture: None,
+            ram_backed: None,
+            quota: None,
+            quota_check_interval: DEFAULT_QUOTA_CHECK_INTERVAL,
+            keep_on_panic: true,
+            keep,
+        }
+    }
+
+    ///
+    /// Names the directory deterministically after the given name, e.g. `{temp_dir}/aleo_storage_{name}`,
+    /// rather than randomly. Characters other than `A-Za-z0-9_-` are replaced by `_`.
+    ///
+    /// A directory left behind by a previous run with the same name is removed,
+    /// so the name must be unique among the tests running concurrently.
+    ///
+    pub fn name(mut self, name: &str) -> Self {
+        let sanitize = |c: char| match c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+            true => c,
+            false => '_',
+        };
+        self.name = Some(name.chars().map(sanitize).collect());
+        self
+    }
+
+    ///
+    /// Names the directory deterministically after the current test (see `name`),
+    /// as given by the name of the current thread, e.g. `tests::test_sync`.
+    ///
+    /// The libtest harness names every test thread after its test. Outside of it, or on the main thread,
+    /// the directory keeps a random name.
+    ///
+    pub fn named_after_current_test(self) -> Self {
+        match std::thread::current().name() {
+            Some(name) if name != "main" => self.name(name),
+            _ => self,
+        }
+    }
+
+    /// Seeds the directory with the contents of the given fixture directory.
+    pub fn fixture<P: Into<PathBuf>>(mut self, fixture: P, mode: FixtureMode) -> Self {
+        self.fixture = Some((fixture.into(), mode));
+        self
+    }
+
+    ///
+    /// Creates the directory on a RAM-backed filesystem, if one is available with at least the given number of bytes
+    /// free, or else in the system's temporary directory.
+    ///
+    /// The RAM-backed filesystem is the one given by the `ALEO_TEST_RAM_DIR` environment variable, or else `/dev/shm`
+    /// if it is a tmpfs. As the space is not reserved, the size is also a cap the test must stay within,
+    /// so that concurrent tests do not exhaust the memory of the host: it is enforced as the quota of the directory
+    /// (see `quota`), unless a lower quota is set.
+    ///
+    pub fn ram_backed(mut self, max_bytes: u64) -> Self {
+        self.ram_backed = Some(max_bytes);
+        self
+    }
+
+    ///
+    /// Limits the total size of the files in the directory to the given number of bytes.
+    ///
+    /// The usage of the directory is measured in the background while the `TestStorage` is alive,
+    /// and a quota being exceeded is reported as soon as it is observed. The `TestStorage` then panics when dropped,
+    /// failing the test, unless the test is already failing, and reports the usage of the directory.
+    /// As the usage is sampled periodically (see `quota_check_interval`), a short-lived excess may go unnoticed.
+    ///
+    pub fn quota(mut self, max_bytes: u64) -> Self {
+        self.quota = Some(max_bytes);
+        self
+    }
+
+    ///
+    /// Sets how often the usage of the directory is measured if it has a quota, which defaults to every second.
+    ///
+    /// Every measurement walks the whole directory, so a shorter interval catches shorter-lived excesses
+    /// at the expense of more I/O.
+    ///
+    pub fn quota_check_interval(mut self, interval: Duration) -> Self {
+        self.quota_check_interval = interval;
+        self
+    }
+
+    /// Sets whether to keep the directory if the `TestStorage` is dropped while panicking, e.g. on a failed assertion.
+    pub fn keep_on_panic(mut self, keep_on_panic: bool) -> Self {
+        self.keep_on_panic = keep_on_panic;
+        self
+    }
+
+    /// Sets whether to always keep the directory, overriding the `ALEO_KEEP_TEST_DIR` environment variable.
+    pub fn keep(mut self, keep: bool) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Creates the test storage directory, seeding it from the fixture if any.
+    pub fn build(self) -> io::Result<TestStorage> {
+        let ram_dir = self.ram_backed.and_then(ram_backed_dir);
+        let parent = ram_dir.clone().unwrap_or_else(std::env::temp_dir);
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(TEST_DIR_PREFIX).disable_cleanup(self.keep);
+        let tempdir = match &self.name {
+            Some(name) => {
+                let prefix = format!("{TEST_DIR_PREFIX}{name}");
+                let path = parent.join(&prefix);
+                for path in custom_resources(&path).iter().chain([&path]) {
+                    remove_path(path)?;
+                }
+                builder.prefix(&prefix).rand_bytes(0).tempdir_in(&parent)?
+            }
+            None => builder.tempdir_in(&parent)?,
+        };
+        if let Some((fixture, mode)) = &self.fixture {
+            seed_dir(fixture, tempdir.path(), *mode)?;
+        }
+        let quota = match (self.quota, self.ram_backed) {
+            (Some(quota), Some(max_bytes)) => Some(quota.min(max_bytes)),
+            (quota, max_bytes) => quota.or(max_bytes),
+        };
+        let monitor = match quota {
+            Some(quota) => Some(QuotaMonitor::spawn(
+                tempdir.path().to_path_buf(),
+                quota,
+                self.quota_check_interval,
+            )?),
+            None => None,
+        };
+        Ok(TestStorage {
+            tempdir: Some(Arc::new(tempdir)),
+            is_ram_backed: ram_dir.is_some(),
+            monitor,
+            keep_on_panic: self.keep_on_panic,
+            keep: self.keep,
+        })
+    }
+}
+
+///
+/// A temporary storage directory for a test, which is removed once the test storage and every `StorageMode::Test`
+/// returned by `mode` are dropped. The resources of the node kept next to it (see `StorageLayout`) are removed
+/// when the test storage is dropped.
+///
+/// If the test panics or exceeds the quota of the directory, the directory is kept and its path printed instead,
+/// provided the test storage is dropped after the modes it returned, e.g. because it is declared before them. Setting the `ALEO_KEEP_TEST_DIR`
+/// environment variable keeps every directory, e.g. to inspect a passing test.
+///
+#[derive(Debug)]
+pub struct TestStorage {
+    /// The temporary directory, which is only `None` while being dropped.
+    tempdir: Option<Arc<TempDir>>,
+    /// Whether the directory is on a RAM-backed filesystem.
+    is_ram_backed: bool,
+    /// The monitor of the quota of the directory, if any.
+    monitor: Option<QuotaMonitor>,
+    /// Whether to keep the directory if dropped while panicking.
+    keep_on_panic: bool,
+    /// Whether the directory is always kept.
+    keep: bool,
+}
+
+impl TestStorage {
+    /// Creates an empty test storage directory with a random name, as given by `TestStorageBuilder`.
+    pub fn new() -> io::Result<Self> {
+        Self::builder().build()
+    }
+
+    /// Returns a builder for a test storage directory.
+    pub fn builder() -> TestStorageBuilder {
+        TestStorageBuilder::new()
+    }
+
+    /// Returns the test storage directory.
+    pub fn path(&self) -> &Path {
+        self.tempdir().path()
+    }
+
+    /// Returns `true` if the test storage directory is on a RAM-backed filesystem (see `TestStorageBuilder::ram_backed`).
+    pub const fn is_ram_backed(&self) -> bool {
+        self.is_ram_backed
+    }
+
+    ///
+    /// Measures the usage of the test storage directory.
+    ///
+    /// The peak usage is the highest usage observed, which is only tracked in the background if there is a quota.
+    ///
+    pub fn usage(&self) -> TestStorageUsage {
+        let bytes = dir_usage(self.path()).map(|(bytes, _)| bytes).ok();
+        match &self.monitor {
+            Some(monitor) => {
+                if let Some(bytes) = bytes {
+                    monitor.stats.record(bytes, monitor.quota, self.path());
+                }
+                monitor.stats.usage(monitor.quota)
+            }
+            None => TestStorageUsage {
+                bytes: bytes.unwrap_or_default(),
+                peak_bytes: bytes.unwrap_or_default(),
+                quota: None,
+            },
+        }
+    }
+
+    ///
+    /// Measures the usage of the test storage directory, and returns an error of kind `QuotaExceeded`
+    /// if it has exceeded its quota at any point.
+    ///
+    pub fn check_quota(&self) -> io::Result<TestStorageUsage> {
+        let usage = self.usage();
+        match usage.is_over_quota() {
+            true => Err(io::Error::new(io::ErrorKind::QuotaExceeded, usage.to_string())),
+            false => Ok(usage),
+        }
+    }
+
+    /// Returns the test mode using the test storage directory.
+    pub fn mode(&self) -> StorageMode {
+        StorageMode::Test(Some(self.tempdir().clone()))
+    }
+
+    /// Returns the temporary directory.
+    fn tempdir(&self) -> &Arc<TempDir> {
+        self.tempdir
+            .as_ref()
+            .expect("the test storage directory is only taken when dropped")
+    }
+}
+
+impl Drop for TestStorage {
+    fn drop(&mut self) {
+        // The usage is measured before the directory may be removed, and the monitor is stopped when it is dropped.
+        let usage = self.monitor.is_some().then(|| self.usage());
+        self.monitor = None;
+        let is_over_quota = usage.as_ref().is_some_and(TestStorageUsage::is_over_quota);
+        let panicking = std::thread::panicking();
+        let Some(tempdir) = self.tempdir.take() else {
+            return;
+        };
+        let path = tempdir.path().to_path_buf();
+        if let Some(usage) = &usage {
+            eprintln!("test storage directory {}: {usage}", path.display());
+        }
+        let is_kept = if self.keep {
+            eprintln!("keeping the test storage directory {}", path.display());
+            true
+        } else if self.keep_on_panic && (panicking || is_over_quota) {
+            match Arc::try_unwrap(tempdir) {
+                Ok(tempdir) => {
+                    eprintln!(
+                        "the test failed, keeping its storage directory {}",
+                        tempdir.keep().display()
+                    );
+                    true
+                }
+                Err(_) => {
+                    eprintln!(
+                        "the test failed, but its storage directory {} is still in use and will be removed",
+                        path.display()
+                    );
+                    false
+                }
+            }
+        } else {
+            false
+        };
+        if !is_kept {
+            for resource in custom_resources(&path) {
+                let _ = remove_path(&resource);
+            }
+        }
+        // Panicking while the test is already failing would abort the process, hiding the original failure.
+        if is_over_quota && !panicking {
+            panic!("the test storage directory exceeded its quota: {}", usage.unwrap());
+        }
+    }
+}
+
+/// The usage of a test storage directory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TestStorageUsage {
+    /// The total size of the files in the directory, in bytes.
+    pub bytes: u64,
+    /// The highest total size of the files in the directory that was observed, in bytes.
+    pub peak_bytes: u64,
+    /// The quota of the directory, if any.
+    pub quota: Option<u64>,
+}
+
+impl TestStorageUsage {
+    /// Returns `true` if the directory exceeded its quota at any point it was observed.
+    pub fn is_over_quota(&self) -> bool {
+        self.quota.is_some_and(|quota| self.peak_bytes > quota)
+    }
+}
+
+impl fmt::Display for TestStorageUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes used, {} bytes at peak", self.bytes, self.peak_bytes)?;
+        match self.quota {
+            Some(quota) => write!(f, ", of a {quota}-byte quota"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The usage of a test storage directory, as measured by its `QuotaMonitor`.
+#[derive(Debug, Default)]
+struct QuotaStats {
+    /// The last measured usage, in bytes.
+    bytes: AtomicU64,
+    /// The highest measured usage, in bytes.
+    peak_bytes: AtomicU64,
+    /// Whether the quota was exceeded, which is reported once.
+    exceeded: AtomicBool,
+}
+
+impl QuotaStats {
+    /// Records the given usage of the given directory, reporting if it exceeds the quota for the first time.
+    fn record(&self, bytes: u64, quota: u64, dir: &Path) {
+        self.bytes.store(bytes, Ordering::Relaxed);
+        self.peak_bytes.fetch_max(bytes, Ordering::Relaxed);
+        if bytes > quota && !self.exceeded.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "the test storage directory {} exceeded its quota: {bytes} bytes used, of a {quota}-byte quota",
+                dir.display()
+            );
+        }
+    }
+
+    /// Returns the recorded usage.
+    fn usage(&self, quota: u64) -> TestStorageUsage {
+        TestStorageUsage {
+            bytes: self.bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            quota: Some(quota),
+        }
+    }
+}
+
+/// A background thread that periodically measures the usage of a test storage directory with a quota.
+#[derive(Debug)]
+struct QuotaMonitor {
+    /// The quota, in bytes.
+    quota: u64,
+    /// The measured usage.
+    stats: Arc<QuotaStats>,
+    /// The channel used to stop the thread, which is disconnected when the monitor is dropped.
+    _stop: mpsc::Sender<()>,
+}
+
+impl QuotaMonitor {
+    /// Starts measuring the usage of the given directory.
+    fn spawn(dir: PathBuf, quota: u64, interval: Duration) -> io::Result<Self> {
+        let stats = Arc::new(QuotaStats::default());
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread_stats = stats.clone();
+        thread::Builder::new()
+            .name("aleo-test-storage-quota".to_string())
+            .spawn(move || {
+                loop {
+                    // A measurement fails if a file is removed while it is walked, and is retried after the interval.
+                    if let Ok((bytes, _)) = dir_usage(&dir) {
+                        thread_stats.record(bytes, quota, &dir);
+                    }
+                    if let Err(mpsc::RecvTimeoutError::Disconnected) | Ok(()) = stopped.recv_timeout(interval) {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self {
+            quota,
+            stats,
+            _stop: stop,
+        })
+    }
+}
+
+/// Returns the RAM-backed directory given by the `ALEO_TEST_RAM_DIR` environment variable, or else `/dev/shm`.
+fn ram_dir() -> PathBuf {
+    match std::env::var_os(ALEO_TEST_RAM_DIR_ENV) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(DEFAULT_RAM_DIR),
+    }
+}
+
+/// Returns the RAM-backed directory, if it exists, may be written, and has the given number of bytes free.
+fn ram_backed_dir(max_bytes: u64) -> Option<PathBuf> {
+    let dir = ram_dir();
+    let report = preflight_dir(&dir).ok()?;
+    // The directory given by the environment variable is trusted to be RAM-backed, e.g. a ramdisk on macOS.
+    let is_ram_backed = report.is_tmpfs || std::env::var_os(ALEO_TEST_RAM_DIR_ENV).is_some_and(|dir| !dir.is_empty());
+    let is_usable = report.existing_path == dir && report.is_writable && report.free_bytes >= max_bytes;
+    (is_ram_backed && is_usable).then_some(dir)
+}
+
+/// Returns the directories the test storage directories are created in, i.e. the system's temporary directory,
+/// and the RAM-backed directory if it exists.
+pub(crate) fn test_dir_parents() -> Vec<PathBuf> {
+    let mut parents = vec![std::env::temp_dir()];
+    parents.extend(Some(ram_dir()).filter(|dir| dir.is_dir() && *dir != parents[0]));
+    parents
+}
+
+/// Seeds the given directory with the contents of the given fixture directory.
+fn seed_dir(fixture: &Path, target: &Path, mode: FixtureMode) -> io::Result<()> {
+    for entry in fs::read_dir(fixture)? {
+        let entry = entry?;
+        let target_path = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir(&target_path)?;
+            seed_dir(&entry.path(), &target_path, mode)?;
+        } else if mode == FixtureMode::Copy || fs::hard_link(entry.path(), &target_path).is_err() {
+            fs::copy(entry.path(), &target_path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Network, aleo_network_ledger_dir, tests::env_lock};
+
+    #[test]
+    fn test_test_storage() {
+        let storage = TestStorage::builder().keep(false).build().unwrap();
+        let path = storage.path().to_path_buf();
+        assert!(path.file_name().unwrap().to_str().unwrap().starts_with(TEST_DIR_PREFIX));
+        let mode = storage.mode();
+        assert_eq!(aleo_network_ledger_dir(Network::TESTNET, &mode), path);
+
+        // The directory outlives the test storage while a mode uses it.
+        drop(storage);
+        assert!(path.is_dir());
+        drop(mode);
+        assert!(!path.exists());
+
+        // The resources of the node kept next to the directory are removed along with it.
+        let storage = TestStorage::builder().keep(false).build().unwrap();
+        let layout = crate::StorageLayout::new(Network::TESTNET, storage.mode());
+        fs::create_dir_all(layout.logs_dir()).unwrap();
+        fs::write(layout.node_key_path(), b"APrivateKey1").unwrap();
+        drop(storage);
+        assert!(!layout.logs_dir().exists());
+        assert!(!layout.node_key_path().exists());
+    }
+
+    #[test]
+    fn test_test_storage_name() {
+        let storage = TestStorage::builder()
+            .keep(false)
+            .named_after_current_test()
+            .build()
+            .unwrap();
+        let expected = format!("{TEST_DIR_PREFIX}test_storage__tests__test_test_storage_name");
+        assert_eq!(storage.path(), std::env::temp_dir().join(&expected));
+
+        // A directory left behind by a previous run is replaced.
+        fs::write(storage.path().join("CURRENT"), b"").unwrap();
+        let path = storage.path().to_path_buf();
+        std::mem::forget(storage);
+        let storage = TestStorage::builder()
+            .keep(false)
+            .named_after_current_test()
+            .build()
+            .unwrap();
+        assert_eq!(storage.path(), path);
+        assert!(!path.join("CURRENT").exists());
+    }
+
+    #[test]
+    fn test_test_storage_fixture() {
+        let fixture = tempfile::TempDir::new().unwrap();
+        fs::create_dir(fixture.path().join("blocks")).unwrap();
+        fs::write(fixture.path().join("blocks").join("000001.sst"), b"block").unwrap();
+        fs::write(fixture.path().join("CURRENT"), b"MANIFEST-000001").unwrap();
+
+        for mode in [FixtureMode::Copy, FixtureMode::HardLink] {
+            let storage = TestStorage::builder()
+                .keep(false)
+                .fixture(fixture.path(), mode)
+                .build()
+                .unwrap();
+            assert_eq!(
+                fs::read(storage.path().join("blocks").join("000001.sst")).unwrap(),
+                b"block"
+            );
+            assert_eq!(fs::read(storage.path().join("CURRENT")).unwrap(), b"MANIFEST-000001");
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let links = fs::metadata(fixture.path().join("CURRENT")).unwrap().nlink();
+                assert_eq!(links, if mode == FixtureMode::HardLink { 2 } else { 1 });
+            }
+        }
+    }
+
+    #[test]
+    fn test_test_storage_keep() {
+        // The directory is kept if the test panics.
+        let result = std::panic::catch_unwind(|| {
+            let storage = TestStorage::builder().keep(false).build().unwrap();
+            let _mode = storage.mode();
+            panic!("{}", storage.path().display());
+        });
+        let path = PathBuf::from(*result.unwrap_err().downcast::<String>().unwrap());
+        assert!(path.is_dir());
+        fs::remove_dir(&path).unwrap();
+
+        // The directory is kept if the environment variable is set.
+        let _lock = env_lock();
+        std::env::set_var(ALEO_KEEP_TEST_DIR_ENV, "1");
+        let storage = TestStorage::new().unwrap();
+        std::env::remove_var(ALEO_KEEP_TEST_DIR_ENV);
+        let path = storage.path().to_path_buf();
+        drop(storage);
+        assert!(path.is_dir());
+        fs::remove_dir(&path).unwrap();
+    }
+
+    #[test]
+    fn test_test_storage_ram_backed() {
+        let _lock = env_lock();
+        let ram_dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var(ALEO_TEST_RAM_DIR_ENV, ram_dir.path());
+
+        let storage = TestStorage::builder().keep(false).ram_backed(1 << 20).build().unwrap();
+        assert!(storage.is_ram_backed());
+        assert_eq!(storage.path().parent().unwrap(), ram_dir.path());
+        assert!(test_dir_parents().contains(&ram_dir.path().to_path_buf()));
+
+        // The size is enforced as a quota.
+        let storage = TestStorage::builder()
+            .keep(false)
+            .keep_on_panic(false)
+            .ram_backed(1024)
+            .build()
+            .unwrap();
+        fs::write(storage.path().join("000001.sst"), [0u8; 4096]).unwrap();
+        assert_eq!(storage.usage().quota, Some(1024));
+        assert!(storage.check_quota().is_err());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(storage)));
+        assert!(result.is_err());
+
+        // The test storage falls back to the temporary directory if the RAM-backed filesystem is too small.
+        let storage = TestStorage::builder().keep(false).ram_backed(u64::MAX).build().unwrap();
+        assert!(!storage.is_ram_backed());
+        assert_eq!(storage.path().parent().unwrap(), std::env::temp_dir());
+
+        // Or if it is missing.
+        std::env::set_var(ALEO_TEST_RAM_DIR_ENV, ram_dir.path().join("missing"));
+        let storage = TestStorage::builder().keep(false).ram_backed(1 << 20).build().unwrap();
+        assert!(!storage.is_ram_backed());
+        std::env::remove_var(ALEO_TEST_RAM_DIR_ENV);
+    }
+
+    impl TestStorage {
+        /// Returns the peak usage observed by the quota monitor.
+        fn stats_peak(&self) -> u64 {
+            self.monitor.as_ref().unwrap().stats.peak_bytes.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn test_test_storage_quota() {
+        let interval = Duration::from_millis(10);
+        let storage = TestStorage::builder()
+            .keep(false)
+            .quota(1024)
+            .quota_check_interval(interval)
+            .build()
+            .unwrap();
+        fs::write(storage.path().join("CURRENT"), [0u8; 512]).unwrap();
+        let usage = storage.check_quota().unwrap();
+        assert_eq!(usage, TestStorageUsage {
+            bytes: 512,
+            peak_bytes: 512,
+            quota: Some(1024),
+        });
+
+        // The excess is observed by the monitor, even once it is gone.
+        fs::write(storage.path().join("000001.sst"), [0u8; 4096]).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while storage.stats_peak() <= 1024 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "the monitor did not observe the excess"
+            );
+            thread::sleep(interval);
+        }
+        fs::remove_file(storage.path().join("000001.sst")).unwrap();
+        let error = storage.check_quota().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::QuotaExceeded);
+        assert_eq!(storage.usage().bytes, 512);
+
+        // Dropping the test storage fails the test, and keeps the directory.
+        let path = storage.path().to_path_buf();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(storage)));
+        assert!(result.is_err());
+        assert!(path.is_dir());
+        fs::remove_dir_all(&path).unwrap();
+
+        // Dropping it while the test is already failing reports the original failure, rather than aborting.
+        let result = std::panic::catch_unwind(|| {
+            let storage = TestStorage::builder()
+                .keep(false)
+                .keep_on_panic(false)
+                .quota(0)
+                .build()
+                .unwrap();
+            fs::write(storage.path().join("CURRENT"), [0u8; 512]).unwrap();
+            assert!(storage.usage().is_over_quota());
+            panic!("the original failure");
+        });
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "the original failure");
+
+        // Without a quota, the usage is measured on demand.
+        let storage = TestStorage::builder().keep(false).build().unwrap();
+        fs::write(storage.path().join("CURRENT"), [0u8; 4096]).unwrap();
+        assert_eq!(storage.check_quota().unwrap().bytes, 4096);
+    }
+}
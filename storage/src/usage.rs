@@ -0,0 +1,479 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    Network,
+    StorageLayout,
+    StorageMode,
+    TEST_DIR_PREFIX,
+    aleo_cache_dir,
+    atomic::TEMP_FILE_MARKER,
+    development_root,
+    discovery::{development_ledger_dirs, production_ledger_dirs},
+    layout::development_resources,
+    snapshot::is_restore_dir,
+    test_storage::test_dir_parents,
+};
+
+use std::{
+    collections::HashSet,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+/// A category of the disk usage of Aleo storage.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(rename_all = "snake_case"))]
+pub enum UsageCategory {
+    /// A production ledger.
+    Ledger,
+    /// A development ledger, along with its other resources.
+    DevelopmentLedger,
+    /// The BFT and consensus state of a production node.
+    Bft,
+    /// The snapshots of a production ledger.
+    Snapshots,
+    /// The downloaded resources, such as the proving and verifying keys.
+    Resources,
+    /// The log files of a production node.
+    Logs,
+    /// The files left behind by interrupted writes, snapshots and restores, and the test directories.
+    TempFiles,
+}
+
+impl fmt::Display for UsageCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Ledger => "ledger",
+            Self::DevelopmentLedger => "development_ledger",
+            Self::Bft => "bft",
+            Self::Snapshots => "snapshots",
+            Self::Resources => "resources",
+            Self::Logs => "logs",
+            Self::TempFiles => "temp_files",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The disk usage of a directory of Aleo storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UsageEntry {
+    /// The category of the directory.
+    pub category: UsageCategory,
+    /// The network of the directory, if it belongs to one.
+    pub network: Option<Network>,
+    /// The development ID of the directory, if it belongs to a development ledger.
+    pub dev: Option<u16>,
+    /// The directory.
+    pub path: PathBuf,
+    /// The number of files in the directory.
+    pub files: u64,
+    /// The total size of the files in the directory, in bytes.
+    pub bytes: u64,
+    /// The space allocated on disk for the files in the directory, in bytes.
+    ///
+    /// This may be less than the size of sparse files, and excludes any file already accounted for
+    /// through another hard link, e.g. in a snapshot.
+    pub disk_bytes: u64,
+}
+
+///
+/// The disk usage of Aleo storage, per directory.
+///
+/// With the `serde` feature, the report may be serialized, e.g. as JSON. The network is given by name,
+/// or by ID for a custom network, and a path that is not valid UTF-8 fails to serialize.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UsageReport {
+    /// The usage of each directory found, in the order they were walked.
+    pub entries: Vec<UsageEntry>,
+    /// The directories that could not be read, whose contents are not counted.
+    pub unreadable: Vec<PathBuf>,
+    /// Whether the walk completed, rather than being interrupted.
+    pub complete: bool,
+}
+
+impl UsageReport {
+    /// Returns the total space allocated on disk, in bytes.
+    pub fn total_disk_bytes(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.disk_bytes).sum()
+    }
+
+    /// Returns the space allocated on disk for the given category, in bytes.
+    pub fn category_disk_bytes(&self, category: UsageCategory) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.category == category)
+            .map(|entry| entry.disk_bytes)
+            .sum()
+    }
+}
+
+///
+/// A walker of the directories of Aleo storage, which reports their disk usage.
+///
+/// The production directories are found in the Aleo directories (see `StorageLayout`), and the development
+/// ledgers in the development roots, which default to the one given by `development_root`.
+/// The walk stops early, with an incomplete report, once the cancellation flag is set.
+///
+#[derive(Clone, Debug)]
+pub struct UsageScanner {
+    /// The roots to search for development ledgers.
+    dev_roots: Vec<PathBuf>,
+    /// The flag that interrupts the walk when set.
+    cancel: Arc<AtomicBool>,
+}
+
+impl Default for UsageScanner {
+    fn default() -> Self {
+        Self {
+            dev_roots: vec![development_root().path],
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl UsageScanner {
+    /// Initializes a new scanner of the production directories and the development root.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the roots to search for development ledgers.
+    pub fn dev_roots<I: IntoIterator<Item = P>, P: Into<PathBuf>>(mut self, roots: I) -> Self {
+        self.dev_roots = roots.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the flag that interrupts the walk when set, e.g. from a signal handler or another thread.
+    pub fn cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Walks the directories of Aleo storage, and returns their disk usage.
+    pub fn scan(&self) -> io::Result<UsageReport> {
+        let mut walker = Walker {
+            cancel: &self.cancel,
+            seen: HashSet::new(),
+            report: UsageReport::default(),
+        };
+
+        // The ledger directories are only enumerated here, and measured by the walker, which may be interrupted.
+        for ledger in production_ledger_dirs()? {
+            let layout = StorageLayout::new(ledger.network, StorageMode::Production);
+            let network = Some(ledger.network);
+            walker.add(UsageCategory::Ledger, network, None, &[ledger.path]);
+            walker.add(UsageCategory::Bft, network, None, &[layout.bft_dir()]);
+            walker.add(UsageCategory::Snapshots, network, None, &[layout.snapshots_dir()]);
+            walker.add(UsageCategory::Logs, network, None, &[layout.logs_dir()]);
+        }
+        walker.add(UsageCategory::Resources, None, None, &[
+            aleo_cache_dir().join("resources")
+        ]);
+
+        for root in &self.dev_roots {
+            for ledger in development_ledger_dirs(root)? {
+                let dev = ledger.dev.unwrap_or_default();
+                let mut paths = vec![ledger.path.clone()];
+                paths.extend(development_resources(&ledger.path, &ledger.network, dev));
+                walker.add(
+                    UsageCategory::DevelopmentLedger,
+                    Some(ledger.network),
+                    ledger.dev,
+                    &paths,
+                );
+            }
+        }
+
+        // The test directories are left behind in the temporary (or RAM-backed) directory by tests that did not clean up.
+        let mut test_dirs = Vec::new();
+        for parent in test_dir_parents() {
+            let Ok(entries) = fs::read_dir(parent) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with(TEST_DIR_PREFIX) {
+                    test_dirs.push(entry.path());
+                }
+            }
+        }
+        walker.add_temp(test_dirs);
+
+        walker.report.complete = !self.cancel.load(Ordering::Relaxed);
+        Ok(walker.report)
+    }
+}
+
+/// Returns the disk usage of Aleo storage, as reported by a default `UsageScanner`.
+pub fn storage_usage() -> io::Result<UsageReport> {
+    UsageScanner::new().scan()
+}
+
+/// The state of a walk of Aleo storage.
+struct Walker<'a> {
+    /// The flag that interrupts the walk when set.
+    cancel: &'a AtomicBool,
+    /// The files with several hard links that have already been accounted for, by device and inode.
+    seen: HashSet<(u64, u64)>,
+    /// The report being built.
+    report: UsageReport,
+}
+
+/// The usage of the files walked, and the temporary files found among them.
+#[derive(Default)]
+struct Usage {
+    files: u64,
+    bytes: u64,
+    disk_bytes: u64,
+    temp_paths: Vec<PathBuf>,
+}
+
+impl Walker<'_> {
+    /// Walks the given paths, and reports their usage under the given category if any of them exists.
+    fn add(&mut self, category: UsageCategory, network: Option<Network>, dev: Option<u16>, paths: &[PathBuf]) {
+        let mut usage = Usage::default();
+        let mut exists = false;
+        for path in paths {
+            exists |= self.walk(path, &mut usage, false);
+        }
+        if exists && !self.is_cancelled() {
+            self.report.entries.push(UsageEntry {
+                category,
+                network,
+                dev,
+                path: paths[0].clone(),
+                files: usage.files,
+                bytes: usage.bytes,
+                disk_bytes: usage.disk_bytes,
+            });
+        }
+        self.add_temp(usage.temp_paths)
+    }
+
+    /// Walks the given temporary files and directories, and reports their usage separately.
+    fn add_temp(&mut self, paths: Vec<PathBuf>) {
+        for path in paths {
+            let mut usage = Usage::default();
+            if self.walk(&path, &mut usage, true) && !self.is_cancelled() {
+                self.report.entries.push(UsageEntry {
+                    category: UsageCategory::TempFiles,
+                    network: None,
+                    dev: None,
+                    path,
+                    files: usage.files,
+                    bytes: usage.bytes,
+                    disk_bytes: usage.disk_bytes,
+                });
+            }
+        }
+    }
+
+    ///
+    /// Walks the given file or directory without following symbolic links, and returns `true` if it exists.
+    /// Unless the path is itself temporary, the temporary files found are set aside rather than counted.
+    ///
+    /// The paths that cannot be read are reported as unreadable, and the walk continues past them.
+    ///
+    fn walk(&mut self, path: &Path, usage: &mut Usage, is_temp: bool) -> bool {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return false,
+            Err(_) => {
+                self.report.unreadable.push(path.to_path_buf());
+                return false;
+            }
+        };
+        if !metadata.is_dir() {
+            self.count(&metadata, usage);
+            return true;
+        }
+
+        let mut pending = vec![path.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                // The directory may be removed while it is walked, e.g. by a running node.
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(_) => {
+                    self.report.unreadable.push(dir);
+                    continue;
+                }
+            };
+            for entry in entries {
+                if self.is_cancelled() {
+                    return true;
+                }
+                let Ok(entry) = entry else { continue };
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if !is_temp
+                    && (name.contains(TEMP_FILE_MARKER)
+                        || is_restore_dir(&entry.file_name())
+                        || name.ends_with(".partial"))
+                {
+                    usage.temp_paths.push(entry.path());
+                    continue;
+                }
+                let Ok(metadata) = entry.metadata() else { continue };
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    self.count(&metadata, usage);
+                }
+            }
+        }
+        true
+    }
+
+    /// Counts the given file, unless it is a hard link to a file already counted.
+    fn count(&mut self, metadata: &fs::Metadata, usage: &mut Usage) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.nlink() > 1 && !self.seen.insert((metadata.dev(), metadata.ino())) {
+                usage.files += 1;
+                usage.bytes += metadata.len();
+                return;
+            }
+            usage.disk_bytes += metadata.blocks() * 512;
+        }
+        #[cfg(not(unix))]
+        {
+            usage.disk_bytes += metadata.len();
+        }
+        usage.files += 1;
+        usage.bytes += metadata.len();
+    }
+
+    /// Returns `true` if the walk was interrupted.
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scoped_aleo_root, write_atomic};
+
+    #[test]
+    fn test_storage_usage() {
+        let aleo = tempfile::TempDir::new().unwrap();
+        let dev_root = tempfile::TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(aleo.path());
+
+        let layout = StorageLayout::new(Network::MAINNET, StorageMode::Production);
+        write_atomic(layout.ledger_dir().join("db").join("000001.sst"), vec![0u8; 10_000]).unwrap();
+        write_atomic(layout.logs_dir().join("node.log"), b"started\n").unwrap();
+        write_atomic(layout.proving_keys_dir().join("credits.prover"), vec![0u8; 5_000]).unwrap();
+        // A temporary file left behind by an interrupted write.
+        fs::write(
+            layout.ledger_dir().join(format!(".CURRENT{TEMP_FILE_MARKER}1-0")),
+            b"12",
+        )
+        .unwrap();
+        write_atomic(dev_root.path().join(".ledger-testnet-1").join("CURRENT"), b"1").unwrap();
+        write_atomic(dev_root.path().join(".bft-testnet-1").join("state"), b"123").unwrap();
+
+        let report = UsageScanner::new().dev_roots([dev_root.path()]).scan().unwrap();
+        assert!(report.complete);
+        let entry = |category| report.entries.iter().find(|entry| entry.category == category).unwrap();
+        assert_eq!(
+            (entry(UsageCategory::Ledger).files, entry(UsageCategory::Ledger).bytes),
+            (1, 10_000)
+        );
+        assert_eq!(entry(UsageCategory::Ledger).network, Some(Network::MAINNET));
+        assert_eq!(entry(UsageCategory::Logs).bytes, 8);
+        assert_eq!(entry(UsageCategory::Resources).bytes, 5_000);
+        assert_eq!(entry(UsageCategory::DevelopmentLedger).bytes, 4);
+        assert_eq!(entry(UsageCategory::DevelopmentLedger).dev, Some(1));
+        assert!(
+            report
+                .entries
+                .iter()
+                .any(|entry| entry.category == UsageCategory::TempFiles && entry.bytes == 2)
+        );
+        assert!(!report.entries.iter().any(|entry| entry.category == UsageCategory::Bft));
+        assert_eq!(
+            report.total_disk_bytes(),
+            report.entries.iter().map(|entry| entry.disk_bytes).sum::<u64>()
+        );
+
+        assert!(report.unreadable.is_empty());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&report).unwrap();
+            assert_eq!(json["complete"], true);
+            assert_eq!(json["entries"][0]["category"], "ledger");
+            assert_eq!(json["entries"][0]["network"], "mainnet");
+            assert_eq!(json["entries"][0]["bytes"], 10_000);
+        }
+
+        // An interrupted walk returns an incomplete report.
+        let report = UsageScanner::new()
+            .dev_roots([dev_root.path()])
+            .cancel_flag(Arc::new(AtomicBool::new(true)))
+            .scan()
+            .unwrap();
+        assert!(!report.complete);
+        assert!(report.entries.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_dir() {
+        let aleo = tempfile::TempDir::new().unwrap();
+        let dev_root = tempfile::TempDir::new().unwrap();
+        let _guard = scoped_aleo_root(aleo.path());
+
+        // A directory nested so deeply that its path is too long to be read, which also affects privileged users.
+        let ledger = dev_root.path().join(".ledger-testnet-1");
+        write_atomic(ledger.join("CURRENT"), b"1").unwrap();
+        let name = "d".repeat(250);
+        fs::create_dir(dev_root.path().join("0")).unwrap();
+        for i in 1..20 {
+            fs::create_dir(dev_root.path().join(i.to_string())).unwrap();
+            fs::rename(
+                dev_root.path().join((i - 1).to_string()),
+                dev_root.path().join(i.to_string()).join(&name),
+            )
+            .unwrap();
+        }
+        fs::rename(dev_root.path().join("19"), ledger.join(&name)).unwrap();
+
+        let report = UsageScanner::new().dev_roots([dev_root.path()]).scan().unwrap();
+        assert!(report.complete);
+        assert_eq!(report.unreadable.len(), 1);
+        assert!(report.unreadable[0].starts_with(&ledger));
+        let entry = report
+            .entries
+            .iter()
+            .find(|entry| entry.category == UsageCategory::DevelopmentLedger)
+            .unwrap();
+        assert_eq!(entry.bytes, 1);
+    }
+}
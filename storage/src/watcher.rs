@@ -0,0 +1,481 @@
+// Copyright (C) 2019-2021 Aleo Systems Inc.
+// This file is part of the aleo-std library.
+
+// The aleo-std library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The aleo-std library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the aleo-std library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{PreflightReport, SnapshotStore, StorageLayout, preflight_dir};
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the watcher thread checks whether it was stopped.
+const TICK: Duration = Duration::from_millis(50);
+
+/// A change to the storage of a node, as observed by a `StorageWatcher`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageEvent {
+    /// A configuration file of the node was created, modified or removed (see `StorageLayout::config_path`).
+    ConfigChanged(PathBuf),
+    /// The ledger directory was created.
+    LedgerCreated(PathBuf),
+    /// The ledger directory was removed.
+    LedgerRemoved(PathBuf),
+    /// A snapshot of the ledger was created (see `SnapshotStore`).
+    SnapshotCreated(String),
+    /// A snapshot of the ledger was removed.
+    SnapshotRemoved(String),
+    /// The free space of the filesystem backing the ledger dropped below the threshold.
+    DiskLow(PreflightReport),
+    /// The free space of the filesystem backing the ledger recovered to the threshold or above.
+    DiskRecovered(PreflightReport),
+}
+
+/// The options of a `StorageWatcher`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// How long to wait after a change is notified for the changes that follow it, before reporting them at once.
+    pub debounce: Duration,
+    /// How often to check for changes that are not notified, e.g. the free space, or every change when polling.
+    pub poll_interval: Duration,
+    /// The free space below which `StorageEvent::DiskLow` is reported, if any.
+    pub min_free_bytes: Option<u64>,
+    /// Whether to poll for changes, even where they may be notified, e.g. on network filesystems.
+    pub force_polling: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(500),
+            poll_interval: Duration::from_secs(5),
+            min_free_bytes: None,
+            force_polling: false,
+        }
+    }
+}
+
+///
+/// A background thread that watches the storage of a node, and delivers the changes as typed events on a channel:
+/// the configuration files being edited, the ledger directory being created or removed, snapshots appearing or
+/// disappearing, and the free space running low.
+///
+/// On Linux, the changes are notified by inotify, and reported once they have been debounced. Elsewhere, or if
+/// inotify is unavailable, the storage is polled instead. The thread is stopped when the watcher is dropped.
+///
+#[derive(Debug)]
+pub struct StorageWatcher {
+    /// The channel of the events.
+    events: mpsc::Receiver<StorageEvent>,
+    /// The channel used to stop the thread, which is disconnected when the watcher is dropped.
+    _stop: mpsc::Sender<()>,
+    /// Whether the storage is polled rather than notified.
+    is_polling: bool,
+}
+
+impl StorageWatcher {
+    /// Starts watching the storage of the given layout.
+    pub fn spawn(layout: &StorageLayout, options: WatchOptions) -> io::Result<Self> {
+        // The paths are resolved on the calling thread, which may have a scoped Aleo root.
+        let targets = Targets::new(layout);
+        let mut notifier = match options.force_polling {
+            true => None,
+            false => Notifier::new().ok(),
+        };
+        let is_polling = notifier.is_none();
+        let (sender, events) = mpsc::channel();
+        let (stop, stopped) = mpsc::channel::<()>();
+        thread::Builder::new()
+            .name("aleo-storage-watcher".to_string())
+            .spawn(move || {
+                let (mut state, _) = State::scan(&targets, &options, None);
+                // Free space that is already low is reported on the first check, like `LowSpaceWatcher` does.
+                state.is_low = false;
+                let (mut last_scan, mut pending) = (Instant::now(), None);
+                if let Some(notifier) = &mut notifier {
+                    targets.watch_dirs().iter().for_each(|dir| notifier.watch(dir));
+                }
+                loop {
+                    // Wait for a notification or a tick, stopping as soon as the watcher is dropped.
+                    let is_notified = match &notifier {
+                        Some(notifier) => notifier.wait(TICK),
+                        None => false,
+                    };
+                    let timeout = if notifier.is_some() { Duration::ZERO } else { TICK };
+                    if let Err(mpsc::RecvTimeoutError::Disconnected) | Ok(()) = stopped.recv_timeout(timeout) {
+                        break;
+                    }
+                    // The changes are reported once the debounce period after the first notification has passed.
+                    if is_notified {
+                        pending.get_or_insert_with(Instant::now);
+                    }
+                    let is_due = last_scan.elapsed() >= options.poll_interval
+                        || pending.is_some_and(|notified: Instant| notified.elapsed() >= options.debounce);
+                    if !is_due {
+                        continue;
+                    }
+                    let (next, events) = state.rescan(&targets, &options);
+                    (state, last_scan, pending) = (next, Instant::now(), None);
+                    // The directories that appeared, or were replaced, since they were last watched are watched anew.
+                    if let Some(notifier) = &mut notifier {
+                        targets.watch_dirs().iter().for_each(|dir| notifier.watch(dir));
+                    }
+                    if events.into_iter().any(|event| sender.send(event).is_err()) {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self {
+            events,
+            _stop: stop,
+            is_polling,
+        })
+    }
+
+    /// Returns the channel of the events.
+    pub fn events(&self) -> &mpsc::Receiver<StorageEvent> {
+        &self.events
+    }
+
+    /// Returns `true` if the storage is polled rather than notified.
+    pub const fn is_polling(&self) -> bool {
+        self.is_polling
+    }
+}
+
+/// The paths watched by a `StorageWatcher`.
+struct Targets {
+    /// The configuration files.
+    config_files: Vec<PathBuf>,
+    /// The ledger directory.
+    ledger_dir: PathBuf,
+    /// The snapshots of the ledger.
+    snapshots: SnapshotStore,
+}
+
+impl Targets {
+    /// Returns the paths watched for the given layout.
+    fn new(layout: &StorageLayout) -> Self {
+        Self {
+            config_files: layout.config_files(),
+            ledger_dir: layout.ledger_dir(),
+            snapshots: SnapshotStore::with_dirs(layout.network(), layout.ledger_dir(), layout.snapshots_dir()),
+        }
+    }
+
+    /// Returns the directories whose entries are watched, which are not watched recursively.
+    fn watch_dirs(&self) -> Vec<&Path> {
+        let snapshots_dir = self.snapshots.snapshots_dir();
+        let mut dirs: Vec<&Path> = self.config_files.iter().filter_map(|file| file.parent()).collect();
+        dirs.extend(self.ledger_dir.parent());
+        dirs.extend(snapshots_dir.parent());
+        dirs.push(snapshots_dir);
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+}
+
+/// The version of a file, which changes whenever the file is modified or replaced.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct FileVersion {
+    /// The modification time.
+    modified: SystemTime,
+    /// The size.
+    len: u64,
+    /// The inode, which changes when the file is replaced, e.g. by `write_atomic`.
+    inode: u64,
+    /// The status change time, in seconds and nanoseconds.
+    changed: (i64, i64),
+}
+
+impl FileVersion {
+    /// Returns the version of the file with the given metadata.
+    fn new(metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let (inode, changed) = {
+            use std::os::unix::fs::MetadataExt;
+            (metadata.ino(), (metadata.ctime(), metadata.ctime_nsec()))
+        };
+        #[cfg(not(unix))]
+        let (inode, changed) = (0, (0, 0));
+        Self {
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            len: metadata.len(),
+            inode,
+            changed,
+        }
+    }
+}
+
+/// The state of the watched paths.
+struct State {
+    /// The version of every configuration file, or `None` if it is missing.
+    config_files: Vec<Option<FileVersion>>,
+    /// Whether the ledger directory exists.
+    ledger_exists: bool,
+    /// The names of the snapshots.
+    snapshots: BTreeSet<String>,
+    /// Whether the free space is below the threshold.
+    is_low: bool,
+}
+
+impl State {
+    /// Scans the watched paths, keeping the previous snapshots if they cannot be listed.
+    fn scan(targets: &Targets, options: &WatchOptions, previous: Option<&Self>) -> (Self, Option<PreflightReport>) {
+        let config_files = targets
+            .config_files
+            .iter()
+            .map(|file| fs::metadata(file).ok().map(|metadata| FileVersion::new(&metadata)))
+            .collect();
+        let snapshots = match targets.snapshots.list() {
+            Ok(snapshots) => snapshots.into_iter().map(|snapshot| snapshot.name).collect(),
+            Err(_) => previous.map(|state| state.snapshots.clone()).unwrap_or_default(),
+        };
+        let report = options
+            .min_free_bytes
+            .and_then(|_| preflight_dir(&targets.ledger_dir).ok());
+        let is_low = match (&report, options.min_free_bytes) {
+            (Some(report), Some(min_free_bytes)) => report.free_bytes < min_free_bytes,
+            _ => previous.is_some_and(|state| state.is_low),
+        };
+        let state = Self {
+            config_files,
+            ledger_exists: targets.ledger_dir.is_dir(),
+            snapshots,
+            is_low,
+        };
+        (state, report)
+    }
+
+    /// Scans the watched paths, returning the new state and the changes since this one.
+    fn rescan(&self, targets: &Targets, options: &WatchOptions) -> (Self, Vec<StorageEvent>) {
+        let (next, report) = Self::scan(targets, options, Some(self));
+        let mut events = Vec::new();
+        for ((file, before), after) in targets
+            .config_files
+            .iter()
+            .zip(&self.config_files)
+            .zip(&next.config_files)
+        {
+            if before != after {
+                events.push(StorageEvent::ConfigChanged(file.clone()));
+            }
+        }
+        match (self.ledger_exists, next.ledger_exists) {
+            (false, true) => events.push(StorageEvent::LedgerCreated(targets.ledger_dir.clone())),
+            (true, false) => events.push(StorageEvent::LedgerRemoved(targets.ledger_dir.clone())),
+            _ => (),
+        }
+        let created = next
+            .snapshots
+            .difference(&self.snapshots)
+            .cloned()
+            .map(StorageEvent::SnapshotCreated);
+        let removed = self
+            .snapshots
+            .difference(&next.snapshots)
+            .cloned()
+            .map(StorageEvent::SnapshotRemoved);
+        events.extend(created.chain(removed));
+        match (self.is_low, next.is_low, report) {
+            (false, true, Some(report)) => events.push(StorageEvent::DiskLow(report)),
+            (true, false, Some(report)) => events.push(StorageEvent::DiskRecovered(report)),
+            _ => (),
+        }
+        (next, events)
+    }
+}
+
+/// A notifier of the changes to the entries of directories, backed by inotify.
+#[cfg(target_os = "linux")]
+struct Notifier {
+    /// The inotify file descriptor.
+    fd: std::os::fd::OwnedFd,
+    /// The device and inode of every watched directory.
+    watched: std::collections::BTreeMap<PathBuf, (u64, u64)>,
+}
+
+#[cfg(target_os = "linux")]
+impl Notifier {
+    /// Initializes a new notifier, watching no directory.
+    fn new() -> io::Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        // SAFETY: `inotify_init1` has no preconditions.
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: The file descriptor was just opened, and is owned by nothing else.
+        Ok(Self {
+            fd: unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) },
+            watched: Default::default(),
+        })
+    }
+
+    ///
+    /// Watches the entries of the given directory, if it exists and is not watched already.
+    ///
+    /// A directory that was removed is no longer watched by inotify, so a directory at the same path is watched again
+    /// if it is a different one, and a directory that could not be watched is retried.
+    ///
+    fn watch(&mut self, dir: &Path) {
+        use std::{
+            ffi::CString,
+            os::{
+                fd::AsRawFd,
+                unix::{ffi::OsStrExt, fs::MetadataExt},
+            },
+        };
+
+        let Ok(metadata) = fs::metadata(dir) else {
+            self.watched.remove(dir);
+            return;
+        };
+        let id = (metadata.dev(), metadata.ino());
+        if self.watched.get(dir) == Some(&id) {
+            return;
+        }
+        let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+            return;
+        };
+        let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO | libc::IN_CLOSE_WRITE;
+        // SAFETY: The file descriptor is open, and the path is a valid C string. A missing directory is an error.
+        match unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), mask) } {
+            wd if wd < 0 => self.watched.remove(dir),
+            _ => self.watched.insert(dir.to_path_buf(), id),
+        };
+    }
+
+    /// Waits up to the given timeout for a change, and returns `true` if any occurred, consuming the notifications.
+    fn wait(&self, timeout: Duration) -> bool {
+        use std::os::fd::AsRawFd;
+
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: The file descriptor is open, and `poll_fd` is valid for the duration of the call.
+        if unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) } <= 0 {
+            return false;
+        }
+        let mut buffer = [0u8; 4096];
+        // SAFETY: The buffer is valid for writes of its length. The file descriptor is non-blocking.
+        while unsafe { libc::read(self.fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) } > 0 {}
+        true
+    }
+}
+
+/// A notifier of the changes to the entries of directories, which is unavailable on this platform.
+#[cfg(not(target_os = "linux"))]
+struct Notifier;
+
+#[cfg(not(target_os = "linux"))]
+impl Notifier {
+    /// Returns an error, as the changes are polled for on this platform.
+    fn new() -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Does nothing, as there is no notifier.
+    fn watch(&mut self, _dir: &Path) {}
+
+    /// Returns `false`, as there is no notifier.
+    fn wait(&self, _timeout: Duration) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Network, StorageMode, scoped_aleo_root};
+
+    /// Receives events until one matches the given predicate, failing after a timeout.
+    fn expect_event(watcher: &StorageWatcher, predicate: impl Fn(&StorageEvent) -> bool) -> StorageEvent {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match watcher.events().recv_timeout(timeout) {
+                Ok(event) if predicate(&event) => return event,
+                Ok(_) => continue,
+                Err(error) => panic!("no matching event was received: {error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_storage_watcher() {
+        for force_polling in [false, true] {
+            let aleo = tempfile::TempDir::new().unwrap();
+            let _guard = scoped_aleo_root(aleo.path());
+            let node = aleo.path().join("node");
+            fs::create_dir(&node).unwrap();
+            fs::write(node.join("CURRENT"), b"MANIFEST-000001").unwrap();
+            let layout = StorageLayout::new(Network::TESTNET, StorageMode::Custom(node.clone()));
+
+            let options = WatchOptions {
+                debounce: Duration::from_millis(20),
+                poll_interval: Duration::from_millis(100),
+                min_free_bytes: None,
+                force_polling,
+            };
+            let watcher = StorageWatcher::spawn(&layout, options).unwrap();
+            assert_eq!(watcher.is_polling(), force_polling || !cfg!(target_os = "linux"));
+
+            crate::write_atomic(aleo.path().join("config.toml"), "[rest]\nport = 3030\n").unwrap();
+            let event = expect_event(&watcher, |event| matches!(event, StorageEvent::ConfigChanged(_)));
+            assert_eq!(event, StorageEvent::ConfigChanged(aleo.path().join("config.toml")));
+            // A rewrite of the same size is reported, as the file was replaced.
+            crate::write_atomic(aleo.path().join("config.toml"), "[rest]\nport = 3031\n").unwrap();
+            let event = expect_event(&watcher, |event| matches!(event, StorageEvent::ConfigChanged(_)));
+            assert_eq!(event, StorageEvent::ConfigChanged(aleo.path().join("config.toml")));
+
+            SnapshotStore::new(Network::TESTNET, layout.mode())
+                .create("before-upgrade")
+                .unwrap();
+            let event = expect_event(&watcher, |event| matches!(event, StorageEvent::SnapshotCreated(_)));
+            assert_eq!(event, StorageEvent::SnapshotCreated("before-upgrade".to_string()));
+
+            fs::remove_dir_all(&node).unwrap();
+            let event = expect_event(&watcher, |event| matches!(event, StorageEvent::LedgerRemoved(_)));
+            assert_eq!(event, StorageEvent::LedgerRemoved(node));
+        }
+    }
+
+    #[test]
+    fn test_storage_watcher_disk_low() {
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let layout = StorageLayout::new(Network::TESTNET, StorageMode::Custom(tempdir.path().to_path_buf()));
+        let options = WatchOptions {
+            poll_interval: Duration::from_millis(20),
+            min_free_bytes: Some(u64::MAX),
+            ..Default::default()
+        };
+        let watcher = StorageWatcher::spawn(&layout, options).unwrap();
+        let event = expect_event(&watcher, |event| matches!(event, StorageEvent::DiskLow(_)));
+        let StorageEvent::DiskLow(report) = event else {
+            unreachable!()
+        };
+        assert!(report.free_bytes < u64::MAX);
+    }
+}